@@ -1,31 +1,90 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{program::invoke, system_instruction, clock::Clock};
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::{program::invoke, program_pack::Pack, system_instruction, clock::Clock};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token::{self, native_mint};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{
+    self, CloseAccount, InitializeAccount3, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
+
+pub mod merkle;
+// The reward, lock and commission formulas are the `stake-math` crate's source file, compiled in
+// here rather than taken as a dependency so the program and its clients build the same code.
+// That file must stay free of `crate::` paths and dependencies; its property tests live in
+// crates/stake-math/tests.
+#[path = "../crates/stake-math/src/reward.rs"]
+pub mod reward_math;
+
+use reward_math::{Accumulator, BASIS_POINTS};
 
 declare_id!("5YNT6aKnnaDsUC7PYw3AJoe5GSjK9Gc76Ro79EMMWVSY");
 
-// Constants
-const BASIS_POINTS: u16 = 10000;
-const PRECISION: u128 = 1_000_000_000;
+// Pause flags (StakingPool::pause_flags)
+pub const PAUSE_STAKE: u8 = 1 << 0;
+pub const PAUSE_UNSTAKE: u8 = 1 << 1;
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+pub const PAUSE_REFERRAL_CLAIM: u8 = 1 << 3;
+pub const PAUSE_REFERRAL_LINK: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_STAKE | PAUSE_UNSTAKE | PAUSE_CLAIM | PAUSE_REFERRAL_CLAIM | PAUSE_REFERRAL_LINK;
 
 #[program]
 pub mod solana_staking_referral {
     use super::*;
 
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.authority = ctx.accounts.authority.key();
+        registry.pool_count = 0;
+        registry.bump = ctx.bumps.registry;
+        registry.version = PoolRegistry::VERSION;
+        emit!(RegistryInitialized { registry: registry.key(), authority: registry.authority, timestamp: Clock::get()?.unix_timestamp });
+        Ok(())
+    }
+
     pub fn initialize(
         ctx: Context<Initialize>,
         reward_rate: u64,
         enable_referral: bool,
+        name: String,
+        start_time: i64,
+        end_time: i64,
     ) -> Result<()> {
+        validate_mint_extensions(&ctx.accounts.token_mint)?;
+        validate_mint_extensions(&ctx.accounts.reward_mint)?;
+        require!(name.len() <= PoolRegistryEntry::MAX_NAME_LEN, StakingError::PoolNameTooLong);
+        require!(end_time == 0 || end_time > start_time, StakingError::InvalidPoolSchedule);
+
+        let registry = &mut ctx.accounts.registry;
+        let pool_id = registry.pool_count;
+        registry.pool_count = registry.pool_count.checked_add(1).ok_or(StakingError::MathOverflow)?;
+
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        pool_entry.pool = ctx.accounts.staking_pool.key();
+        pool_entry.pool_id = pool_id;
+        pool_entry.token_mint = ctx.accounts.token_mint.key();
+        pool_entry.reward_mint = ctx.accounts.reward_mint.key();
+        pool_entry.name = name;
+        pool_entry.start_time = start_time;
+        pool_entry.end_time = end_time;
+        pool_entry.status = PoolStatus::Active;
+        pool_entry.bump = ctx.bumps.pool_entry;
+        pool_entry.version = PoolRegistryEntry::VERSION;
+
         let staking_pool = &mut ctx.accounts.staking_pool;
         let authority = &ctx.accounts.authority;
 
+        staking_pool.pool_id = pool_id;
+
         staking_pool.authority = authority.key();
         staking_pool.fee_receiver = authority.key(); 
         
         staking_pool.reward_rate = reward_rate;
         staking_pool.referral_enabled = enable_referral;
-        staking_pool.paused = false;
+        staking_pool.strict_referrer_accounts = false;
+        staking_pool.pause_flags = 0;
         staking_pool.lock_duration = 2592000; // 30 days
 
         staking_pool.referral_l1_bps = 1000;
@@ -36,6 +95,20 @@ pub mod solana_staking_referral {
         staking_pool.fee_unstake = 5_000_000;
         staking_pool.fee_claim = 100_000;
         staking_pool.min_stake_amount = 1_000_000;
+        staking_pool.max_stake_per_wallet = 0;
+        staking_pool.max_total_staked = 0;
+        staking_pool.allowlist_root = [0u8; 32];
+        staking_pool.min_referee_stake = 0;
+        staking_pool.min_referrer_stake_age = 0;
+        staking_pool.commission_vesting_cliff = 0;
+        staking_pool.commission_vesting_duration = 0; // 0 = instant claims
+        staking_pool.restake_pool = Pubkey::default();
+
+        staking_pool.start_time = start_time;
+        staking_pool.end_time = end_time; // 0 = open-ended
+        staking_pool.sunset = false;
+        staking_pool.emergency_mode = false;
+        staking_pool.reward_liability = 0;
 
         staking_pool.last_update_time = Clock::get()?.unix_timestamp;
         staking_pool.reward_per_token_stored = 0;
@@ -46,7 +119,20 @@ pub mod solana_staking_referral {
         staking_pool.staking_vault = ctx.accounts.staking_vault.key();
         staking_pool.reward_vault = ctx.accounts.reward_vault.key();
         staking_pool.bump = ctx.bumps.staking_pool;
-
+        staking_pool.version = StakingPool::VERSION;
+
+        emit!(PoolInitialized {
+            pool: staking_pool.key(),
+            pool_id,
+            authority: staking_pool.authority,
+            token_mint: staking_pool.token_mint,
+            reward_mint: staking_pool.reward_mint,
+            reward_rate,
+            referral_enabled: enable_referral,
+            start_time,
+            end_time,
+            timestamp: staking_pool.last_update_time,
+        });
         Ok(())
     }
 
@@ -57,9 +143,11 @@ pub mod solana_staking_referral {
         ctx: Context<StakeWithReferral>,
         amount: u64,
         referrer: Option<Pubkey>,
+        allowlist_proof: Option<AllowlistProof>,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
-        require!(!pool.paused, StakingError::PoolPaused);
+        require!(!pool.is_paused(PAUSE_STAKE), StakingError::PoolPaused);
+        pool.check_accepting_stakes(Clock::get()?.unix_timestamp)?;
         require!(amount > 0, StakingError::ZeroAmount);
         require!(amount >= pool.min_stake_amount, StakingError::BelowMinimumStake);
 
@@ -80,152 +168,228 @@ pub mod solana_staking_referral {
         //     )?;
         // }
 
+        // Transfer first and credit what the vault actually received (transfer-fee mints deliver less than `amount`)
+        let vault_balance_before = ctx.accounts.staking_vault.amount;
+        if pool.is_native() && ctx.accounts.user_token_account.is_none() {
+            // Native SOL pool: wrap lamports straight from the wallet into the vault
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: ctx.accounts.staking_vault.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+            token_interface::sync_native(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                SyncNative { account: ctx.accounts.staking_vault.to_account_info() },
+            ))?;
+        } else {
+            let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(StakingError::MissingTokenAccount)?;
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: user_token_account.to_account_info(),
+                        mint: ctx.accounts.token_mint.to_account_info(),
+                        to: ctx.accounts.staking_vault.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                amount,
+                ctx.accounts.token_mint.decimals,
+            )?;
+        }
+
+        ctx.accounts.staking_vault.reload()?;
+        let net_amount = ctx.accounts.staking_vault.amount.checked_sub(vault_balance_before).ok_or(StakingError::MathOverflow)?;
+        require!(net_amount > 0, StakingError::ZeroAmount);
+
         let user_stake = &mut ctx.accounts.user_stake;
         let clock = Clock::get()?;
 
         if user_stake.user == Pubkey::default() {
              user_stake.user = ctx.accounts.user.key();
              user_stake.pool = pool.key();
+             user_stake.version = UserStake::VERSION;
         }
 
+        check_allowlist(pool, user_stake, allowlist_proof)?;
+
         // 2. LINKING LOGIC (Only runs once per user)
-        if pool.referral_enabled && user_stake.referrer.is_none() {
+        // Anti-sybil: the link only counts once the referee holds at least `min_referee_stake`.
+        let stake_after = user_stake.staked_amount.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
+        // Paused linking just skips the link; the stake itself still goes through
+        if pool.referral_enabled && !pool.is_paused(PAUSE_REFERRAL_LINK) && user_stake.referrer.is_none() && stake_after >= pool.min_referee_stake {
              if let Some(ref_key) = referrer {
-                 require!(ref_key != ctx.accounts.user.key(), StakingError::SelfReferral);
+                 let user_key = ctx.accounts.user.key();
+                 require!(ref_key != user_key, StakingError::SelfReferral);
+
+                 // Load the referrer's own stake. It's required: without it the cycle and stake-age checks
+                 // below couldn't run, so a referrer has to hold a stake in this pool.
+                 let ref_stake_info = ctx.accounts.referrer_user_stake.as_ref().ok_or(StakingError::InvalidReferrerAccount)?;
+                 let (expected_stake_pda, _) = Pubkey::find_program_address(
+                     &[b"user_stake", ref_key.as_ref(), pool.key().as_ref()], ctx.program_id
+                 );
+                 require!(ref_stake_info.key() == expected_stake_pda, StakingError::InvalidReferrerAccount);
+                 let ref_stake = UserStake::try_deserialize(&mut &ref_stake_info.data.borrow()[..])?;
+                 require!(!ref_stake.frozen, StakingError::ReferrerFrozen);
+
+                 // Anti-sybil: referrer must have kept a stake of their own for `min_referrer_stake_age`.
+                 // Stakes from before `first_stake_time` was recorded count as too young until migrated.
+                 if pool.min_referrer_stake_age > 0 {
+                      let stake_age = clock.unix_timestamp.checked_sub(ref_stake.first_stake_time).unwrap_or(0);
+                      require!(
+                          ref_stake.first_stake_time > 0 && ref_stake.staked_amount > 0 && stake_age >= pool.min_referrer_stake_age,
+                          StakingError::ReferrerStakeTooYoung
+                      );
+                 }
                  
                  // Save L1
                  user_stake.referrer = Some(ref_key);
 
-                 // Increment Referral Count for L1
-                 if let Some(stats) = &mut ctx.accounts.referrer_stats {
-                      // Verify PDA
-                      let (expected_pda, _) = Pubkey::find_program_address(
-                          &[b"referrer_stats", ref_key.as_ref()], ctx.program_id
-                      );
-                      require!(stats.key() == expected_pda, StakingError::InvalidReferrerAccount);
-                      stats.total_referrals = stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
-                 }
+                 // Increment Referral Count for L1. The stats are required so a frozen referrer
+                 // can't be linked to by leaving them out.
+                 let stats = ctx.accounts.referrer_stats.as_mut().ok_or(StakingError::InvalidReferrerAccount)?;
+                 let (expected_pda, _) = Pubkey::find_program_address(
+                     &[b"referrer_stats", pool.key().as_ref(), ref_key.as_ref()], ctx.program_id
+                 );
+                 require!(stats.key() == expected_pda, StakingError::InvalidReferrerAccount);
+                 require!(!stats.frozen, StakingError::ReferrerFrozen);
+                 stats.total_referrals = stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
 
                  // Save L2 (if exists)
-                 if let Some(ref_stake_info) = &ctx.accounts.referrer_user_stake {
-                      let (expected_stake_pda, _) = Pubkey::find_program_address(
-                          &[b"user_stake", ref_key.as_ref(), pool.key().as_ref()], ctx.program_id
-                      );
-                      require!(ref_stake_info.key() == expected_stake_pda, StakingError::InvalidReferrerAccount);
-
-                      let ref_stake_data = UserStake::try_deserialize(&mut &ref_stake_info.data.borrow()[..])?;
-                      if let Some(l2_key) = ref_stake_data.referrer {
-                          user_stake.referrer_l2 = Some(l2_key);
-                          
-                          // Increment Referral Count for L2
-                          if let Some(l2_stats) = &mut ctx.accounts.referrer_stats_l2 {
-                               let (expected_l2_pda, _) = Pubkey::find_program_address(
-                                   &[b"referrer_stats", l2_key.as_ref()], ctx.program_id
-                               );
-                               require!(l2_stats.key() == expected_l2_pda, StakingError::InvalidReferrerAccount);
-                               l2_stats.total_referrals = l2_stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
-                          }
-
-                          // Save L3 (if exists)
-                          if let Some(l2_stake_info) = &ctx.accounts.l2_user_stake {
-                               let (expected_l2_stake_pda, _) = Pubkey::find_program_address(
-                                   &[b"user_stake", l2_key.as_ref(), pool.key().as_ref()], ctx.program_id
-                               );
-                               require!(l2_stake_info.key() == expected_l2_stake_pda, StakingError::InvalidReferrerAccount);
-
-                               let l2_stake_data = UserStake::try_deserialize(&mut &l2_stake_info.data.borrow()[..])?;
-                               if let Some(l3_key) = l2_stake_data.referrer {
-                                   user_stake.referrer_l3 = Some(l3_key);
-
-                                   // Increment Referral Count for L3
-                                   if let Some(l3_stats) = &mut ctx.accounts.referrer_stats_l3 {
-                                        let (expected_l3_pda, _) = Pubkey::find_program_address(
-                                            &[b"referrer_stats", l3_key.as_ref()], ctx.program_id
-                                        );
-                                        require!(l3_stats.key() == expected_l3_pda, StakingError::InvalidReferrerAccount);
-                                        l3_stats.total_referrals = l3_stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
-                                   }
-                               }
+                 if let Some(l2_key) = ref_stake.referrer {
+                     // Reject rings: L2 may be neither the user nor L1
+                     require!(l2_key != user_key && l2_key != ref_key, StakingError::ReferralCycle);
+                     user_stake.referrer_l2 = Some(l2_key);
+                     
+                     // Increment Referral Count for L2
+                     if let Some(l2_stats) = &mut ctx.accounts.referrer_stats_l2 {
+                          let (expected_l2_pda, _) = Pubkey::find_program_address(
+                              &[b"referrer_stats", pool.key().as_ref(), l2_key.as_ref()], ctx.program_id
+                          );
+                          require!(l2_stats.key() == expected_l2_pda, StakingError::InvalidReferrerAccount);
+                          l2_stats.total_referrals = l2_stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
+                     }
+
+                     // Save L3 (if exists). Strict mode needs L2's stake so L3 can't be dropped by leaving it out.
+                     require!(ctx.accounts.l2_user_stake.is_some() || !pool.strict_referrer_accounts, StakingError::InvalidReferrerAccount);
+                     if let Some(l2_stake_info) = &ctx.accounts.l2_user_stake {
+                          let (expected_l2_stake_pda, _) = Pubkey::find_program_address(
+                              &[b"user_stake", l2_key.as_ref(), pool.key().as_ref()], ctx.program_id
+                          );
+                          require!(l2_stake_info.key() == expected_l2_stake_pda, StakingError::InvalidReferrerAccount);
+
+                          let l2_stake_data = UserStake::try_deserialize(&mut &l2_stake_info.data.borrow()[..])?;
+                          if let Some(l3_key) = l2_stake_data.referrer {
+                              // Reject rings: L3 may not repeat anyone already in the chain
+                              require!(l3_key != user_key && l3_key != ref_key && l3_key != l2_key, StakingError::ReferralCycle);
+                              user_stake.referrer_l3 = Some(l3_key);
+
+                              // Increment Referral Count for L3
+                              if let Some(l3_stats) = &mut ctx.accounts.referrer_stats_l3 {
+                                   let (expected_l3_pda, _) = Pubkey::find_program_address(
+                                       &[b"referrer_stats", pool.key().as_ref(), l3_key.as_ref()], ctx.program_id
+                                   );
+                                   require!(l3_stats.key() == expected_l3_pda, StakingError::InvalidReferrerAccount);
+                                   l3_stats.total_referrals = l3_stats.total_referrals.checked_add(1).ok_or(StakingError::MathOverflow)?;
+                              }
                           }
-                      }
+                     }
                  }
-                 emit!(NewReferral { user: ctx.accounts.user.key(), referrer: ref_key, timestamp: clock.unix_timestamp });
+                 emit!(NewReferral {
+                     user: user_key,
+                     pool: pool.key(),
+                     referrer: ref_key,
+                     referrer_l2: user_stake.referrer_l2,
+                     referrer_l3: user_stake.referrer_l3,
+                     timestamp: clock.unix_timestamp,
+                 });
              }
         }
 
         // 3. ACTIVE STAKE UPDATE LOGIC (Runs on EVERY stake, even top-ups)
         // We separate this from the linking logic so stats update when users add more funds.
         if pool.referral_enabled {
+            // Strict mode: every upline the stake has must be passed in, so its active stake can't be skipped
+            referrer_stats_matches(&ctx.accounts.referrer_stats, &pool.key(), user_stake.referrer, pool.strict_referrer_accounts, ctx.program_id)?;
+            referrer_stats_matches(&ctx.accounts.referrer_stats_l2, &pool.key(), user_stake.referrer_l2, pool.strict_referrer_accounts, ctx.program_id)?;
+            referrer_stats_matches(&ctx.accounts.referrer_stats_l3, &pool.key(), user_stake.referrer_l3, pool.strict_referrer_accounts, ctx.program_id)?;
+
             // Update L1 Active Stake
             if let Some(l1_key) = user_stake.referrer {
                 if let Some(stats) = &mut ctx.accounts.referrer_stats {
                     // Check if the provided stats account matches the user's referrer
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l1_key.as_ref()], ctx.program_id);
+                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", pool.key().as_ref(), l1_key.as_ref()], ctx.program_id);
                     if stats.key() == expected_pda {
-                        stats.active_stake_l1 = stats.active_stake_l1.checked_add(amount).ok_or(StakingError::MathOverflow)?;
-                        stats.volume_referred = stats.volume_referred.checked_add(amount).ok_or(StakingError::MathOverflow)?; // Track lifetime volume too
+                        stats.active_stake_l1 = stats.active_stake_l1.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
+                        stats.volume_referred = stats.volume_referred.checked_add(net_amount).ok_or(StakingError::MathOverflow)?; // Track lifetime volume too
                     }
                 }
             }
             // Update L2 Active Stake
             if let Some(l2_key) = user_stake.referrer_l2 {
                 if let Some(stats) = &mut ctx.accounts.referrer_stats_l2 {
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l2_key.as_ref()], ctx.program_id);
+                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", pool.key().as_ref(), l2_key.as_ref()], ctx.program_id);
                     if stats.key() == expected_pda {
-                        stats.active_stake_l2 = stats.active_stake_l2.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+                        stats.active_stake_l2 = stats.active_stake_l2.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
                     }
                 }
             }
             // Update L3 Active Stake
             if let Some(l3_key) = user_stake.referrer_l3 {
                 if let Some(stats) = &mut ctx.accounts.referrer_stats_l3 {
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l3_key.as_ref()], ctx.program_id);
+                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", pool.key().as_ref(), l3_key.as_ref()], ctx.program_id);
                     if stats.key() == expected_pda {
-                        stats.active_stake_l3 = stats.active_stake_l3.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+                        stats.active_stake_l3 = stats.active_stake_l3.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
                     }
                 }
             }
         }
 
-        update_rewards_optimized(pool, user_stake, clock.unix_timestamp)?;
-
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.staking_vault.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
-
-        pool.total_staked = pool.total_staked.checked_add(amount).ok_or(StakingError::MathOverflow)?;
-        
-        // Lock Timer Logic
-        let mut new_last_stake_time = clock.unix_timestamp;
-        if user_stake.staked_amount > 0 {
-            let unlock_time = user_stake.last_stake_time.checked_add(pool.lock_duration).unwrap_or(i64::MAX);
-            let remaining_time = if unlock_time > clock.unix_timestamp { unlock_time - clock.unix_timestamp } else { 0 };
-            let old_weight = user_stake.staked_amount as u128 * remaining_time as u128;
-            let new_weight = amount as u128 * pool.lock_duration as u128;
-            let total_amount = user_stake.staked_amount as u128 + amount as u128;
-            let weighted_remaining = (old_weight + new_weight) / total_amount;
-            new_last_stake_time = clock.unix_timestamp.checked_sub(pool.lock_duration - weighted_remaining as i64).unwrap_or(clock.unix_timestamp);
+        // 4. COMPETITION VOLUME
+        // The pool's active round is required until it settles, so referred volume can't be kept out of it
+        // by leaving its accounts off. L1's entry is checked against its PDA; a referrer who never entered has none.
+        if pool.active_competition != Pubkey::default() {
+            let competition = ctx.accounts.competition.as_mut().ok_or(StakingError::CompetitionAccountsRequired)?;
+            require_keys_eq!(competition.key(), pool.active_competition, StakingError::InvalidCompetition);
+            if let Some(l1_key) = user_stake.referrer.filter(|_| pool.referral_enabled && competition.is_live(clock.unix_timestamp)) {
+                let entry_info = ctx.accounts.competition_entry.as_ref().ok_or(StakingError::CompetitionAccountsRequired)?.to_account_info();
+                let (expected_entry, _) = Pubkey::find_program_address(&[b"competition_entry", competition.key().as_ref(), l1_key.as_ref()], ctx.program_id);
+                require_keys_eq!(entry_info.key(), expected_entry, StakingError::InvalidCompetition);
+                if entry_info.owner == ctx.program_id {
+                    let mut entry = Account::<CompetitionEntry>::try_from(&entry_info)?;
+                    entry.volume = entry.volume.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
+                    competition.total_volume = competition.total_volume.checked_add(net_amount).ok_or(StakingError::MathOverflow)?;
+                    competition.record_volume(l1_key, entry.volume);
+                    entry.exit(ctx.program_id)?;
+                }
+            }
         }
 
-        user_stake.staked_amount = user_stake.staked_amount.checked_add(amount).ok_or(StakingError::MathOverflow)?;
-        user_stake.last_stake_time = new_last_stake_time;
+        update_rewards_optimized(pool, user_stake, clock.unix_timestamp)?;
+
+        apply_stake(pool, user_stake, net_amount, clock.unix_timestamp)?;
 
-        emit!(Staked { user: ctx.accounts.user.key(), amount, timestamp: clock.unix_timestamp });
+        emit!(Staked {
+            user: ctx.accounts.user.key(),
+            pool: pool.key(),
+            amount,
+            net_amount,
+            user_staked: user_stake.staked_amount,
+            total_staked: pool.total_staked,
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+    pub fn claim_rewards(ctx: Context<ClaimRewards>, destination: ClaimDestination) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
-        require!(!pool.paused, StakingError::PoolPaused);
+        require!(!pool.is_paused(PAUSE_CLAIM), StakingError::ClaimsPaused);
 
         // if pool.fee_claim > 0 {
         //     invoke(
@@ -236,91 +400,398 @@ pub mod solana_staking_referral {
 
         let user_stake = &mut ctx.accounts.user_stake;
         let clock = Clock::get()?;
+        require!(!user_stake.frozen, StakingError::AccountFrozen);
 
         update_rewards_optimized(pool, user_stake, clock.unix_timestamp)?;
         let total_reward = user_stake.reward_pending;
         require!(total_reward > 0, StakingError::NoRewardsToClaim);
 
-        if pool.referral_enabled {
-            if let Some(l1_key) = user_stake.referrer {
-                if let Some(l1_stats) = &mut ctx.accounts.referrer_stats_l1 {
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l1_key.as_ref()], ctx.program_id);
-                    if l1_stats.key() == expected_pda {
-                         let comm = (total_reward as u128 * pool.referral_l1_bps as u128 / BASIS_POINTS as u128) as u64;
-                         if comm > 0 {
-                             l1_stats.total_commission_earned = l1_stats.total_commission_earned.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                             l1_stats.pending_rewards = l1_stats.pending_rewards.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                         }
-                    }
-                }
+        // Referees below `min_referee_stake` don't generate commission
+        if pool.referral_enabled && user_stake.staked_amount >= pool.min_referee_stake {
+            credit_upline_commission(
+                pool,
+                user_stake,
+                total_reward,
+                &mut ctx.accounts.referrer_stats_l1,
+                &mut ctx.accounts.referrer_stats_l2,
+                &mut ctx.accounts.referrer_stats_l3,
+                ctx.program_id,
+            )?;
+        }
+
+        user_stake.reward_pending = 0;
+        user_stake.total_earned = user_stake.total_earned.checked_add(total_reward).ok_or(StakingError::MathOverflow)?;
+        pool.reward_liability = pool.reward_liability.checked_sub(total_reward).ok_or(StakingError::MathOverflow)?;
+
+        // Transfer-fee mints withhold part of every payout; the user (or target stake) receives the net
+        let net_reward = total_reward.checked_sub(transfer_fee(&ctx.accounts.reward_mint, total_reward)?).ok_or(StakingError::MathOverflow)?;
+
+        let pool_signer = pool.signer_seeds();
+        let pool_seeds: &[&[u8]] = &pool_signer.as_seeds();
+        match destination {
+            ClaimDestination::Wallet => {
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.reward_vault.to_account_info(),
+                            mint: ctx.accounts.reward_mint.to_account_info(),
+                            to: ctx.accounts.user_reward_account.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[pool_seeds],
+                    ),
+                    total_reward,
+                    ctx.accounts.reward_mint.decimals,
+                )?;
             }
-            if let Some(l2_key) = user_stake.referrer_l2 {
-                if let Some(l2_stats) = &mut ctx.accounts.referrer_stats_l2 {
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l2_key.as_ref()], ctx.program_id);
-                    if l2_stats.key() == expected_pda {
-                        let comm = (total_reward as u128 * pool.referral_l2_bps as u128 / BASIS_POINTS as u128) as u64;
-                        if comm > 0 {
-                            l2_stats.total_commission_earned = l2_stats.total_commission_earned.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                            l2_stats.pending_rewards = l2_stats.pending_rewards.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                        }
-                    }
+            ClaimDestination::RestakeThisPool => {
+                require!(pool.token_mint == pool.reward_mint, StakingError::InvalidRestakeTarget);
+                require!(!pool.is_paused(PAUSE_STAKE), StakingError::PoolPaused);
+                pool.check_accepting_stakes(clock.unix_timestamp)?;
+                let staking_vault = ctx.accounts.staking_vault.as_ref().ok_or(StakingError::InvalidRestakeTarget)?;
+                require!(staking_vault.key() == pool.staking_vault, StakingError::InvalidRestakeTarget);
+
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.reward_vault.to_account_info(),
+                            mint: ctx.accounts.reward_mint.to_account_info(),
+                            to: staking_vault.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[pool_seeds],
+                    ),
+                    total_reward,
+                    ctx.accounts.reward_mint.decimals,
+                )?;
+
+                // user_stake is already settled up to `now`, so only the principal changes
+                apply_stake(pool, user_stake, net_reward, clock.unix_timestamp)?;
+                if pool.referral_enabled {
+                    add_active_stake(&mut ctx.accounts.referrer_stats_l1, &pool.key(), user_stake.referrer, 1, net_reward, pool.strict_referrer_accounts, ctx.program_id)?;
+                    add_active_stake(&mut ctx.accounts.referrer_stats_l2, &pool.key(), user_stake.referrer_l2, 2, net_reward, pool.strict_referrer_accounts, ctx.program_id)?;
+                    add_active_stake(&mut ctx.accounts.referrer_stats_l3, &pool.key(), user_stake.referrer_l3, 3, net_reward, pool.strict_referrer_accounts, ctx.program_id)?;
                 }
+                emit!(RewardsRestaked { user: ctx.accounts.user.key(), from_pool: pool.key(), to_pool: pool.key(), amount: total_reward, net_amount: net_reward, timestamp: clock.unix_timestamp });
             }
-             if let Some(l3_key) = user_stake.referrer_l3 {
-                if let Some(l3_stats) = &mut ctx.accounts.referrer_stats_l3 {
-                    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l3_key.as_ref()], ctx.program_id);
-                    if l3_stats.key() == expected_pda {
-                        let comm = (total_reward as u128 * pool.referral_l3_bps as u128 / BASIS_POINTS as u128) as u64;
-                        if comm > 0 {
-                            l3_stats.total_commission_earned = l3_stats.total_commission_earned.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                            l3_stats.pending_rewards = l3_stats.pending_rewards.checked_add(comm).ok_or(StakingError::MathOverflow)?;
-                        }
-                    }
+            ClaimDestination::RestakeOtherPool => {
+                let target_pool = ctx.accounts.target_pool.as_mut().ok_or(StakingError::InvalidRestakeTarget)?;
+                require!(
+                    pool.restake_pool != Pubkey::default() && target_pool.key() == pool.restake_pool && target_pool.token_mint == pool.reward_mint,
+                    StakingError::InvalidRestakeTarget
+                );
+                require!(!target_pool.is_paused(PAUSE_STAKE), StakingError::PoolPaused);
+                target_pool.check_accepting_stakes(clock.unix_timestamp)?;
+                let target_stake_allowed = ctx.accounts.target_user_stake.as_ref()
+                    .map_or(false, |target_stake| target_pool.allowlist_root == [0u8; 32] || target_stake.allowlist_root == target_pool.allowlist_root);
+                require!(target_stake_allowed, StakingError::AllowlistProofRequired);
+
+                let target_stake = ctx.accounts.target_user_stake.as_mut().ok_or(StakingError::InvalidRestakeTarget)?;
+                require!(target_stake.user == ctx.accounts.user.key() && target_stake.pool == target_pool.key(), StakingError::InvalidRestakeTarget);
+                let target_vault = ctx.accounts.target_staking_vault.as_ref().ok_or(StakingError::InvalidRestakeTarget)?;
+                require!(target_vault.key() == target_pool.staking_vault, StakingError::InvalidRestakeTarget);
+
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.reward_vault.to_account_info(),
+                            mint: ctx.accounts.reward_mint.to_account_info(),
+                            to: target_vault.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[pool_seeds],
+                    ),
+                    total_reward,
+                    ctx.accounts.reward_mint.decimals,
+                )?;
+
+                update_rewards_optimized(target_pool, target_stake, clock.unix_timestamp)?;
+                apply_stake(target_pool, target_stake, net_reward, clock.unix_timestamp)?;
+                if target_pool.referral_enabled {
+                    add_active_stake(&mut ctx.accounts.target_referrer_stats_l1, &target_pool.key(), target_stake.referrer, 1, net_reward, target_pool.strict_referrer_accounts, ctx.program_id)?;
+                    add_active_stake(&mut ctx.accounts.target_referrer_stats_l2, &target_pool.key(), target_stake.referrer_l2, 2, net_reward, target_pool.strict_referrer_accounts, ctx.program_id)?;
+                    add_active_stake(&mut ctx.accounts.target_referrer_stats_l3, &target_pool.key(), target_stake.referrer_l3, 3, net_reward, target_pool.strict_referrer_accounts, ctx.program_id)?;
                 }
+                emit!(RewardsRestaked { user: ctx.accounts.user.key(), from_pool: pool.key(), to_pool: target_pool.key(), amount: total_reward, net_amount: net_reward, timestamp: clock.unix_timestamp });
+            }
+        }
+
+        emit!(RewardsClaimed {
+            user: ctx.accounts.user.key(),
+            pool: pool.key(),
+            amount: total_reward,
+            net_amount: net_reward,
+            reward_liability: pool.reward_liability,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // -----------------------------------------------------
+    // CLAIM ALL (Staking reward + own referral commission)
+    // -----------------------------------------------------
+    pub fn claim_all(ctx: Context<ClaimAll>) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        require!(!pool.is_paused(PAUSE_CLAIM), StakingError::ClaimsPaused);
+
+        let user_stake = &mut ctx.accounts.user_stake;
+        let clock = Clock::get()?;
+        require!(!user_stake.frozen, StakingError::AccountFrozen);
+
+        update_rewards_optimized(pool, user_stake, clock.unix_timestamp)?;
+        let staking_reward = user_stake.reward_pending;
+
+        if staking_reward > 0 && pool.referral_enabled && user_stake.staked_amount >= pool.min_referee_stake {
+            credit_upline_commission(
+                pool,
+                user_stake,
+                staking_reward,
+                &mut ctx.accounts.referrer_stats_l1,
+                &mut ctx.accounts.referrer_stats_l2,
+                &mut ctx.accounts.referrer_stats_l3,
+                ctx.program_id,
+            )?;
+        }
+
+        // Own commission is only paid out here when it doesn't have to vest and referral claims are open
+        let mut referral_reward = 0;
+        if pool.commission_vesting_duration == 0 && !pool.is_paused(PAUSE_REFERRAL_CLAIM) {
+            if let Some(own_stats) = &mut ctx.accounts.user_referrer_stats {
+                require!(!own_stats.frozen, StakingError::AccountFrozen);
+                referral_reward = own_stats.pending_rewards;
+                own_stats.pending_rewards = 0;
             }
         }
 
+        let total_reward = staking_reward.checked_add(referral_reward).ok_or(StakingError::MathOverflow)?;
+        require!(total_reward > 0, StakingError::NoRewardsToClaim);
+
         user_stake.reward_pending = 0;
-        user_stake.total_earned = user_stake.total_earned.checked_add(total_reward).ok_or(StakingError::MathOverflow)?;
+        user_stake.total_earned = user_stake.total_earned.checked_add(staking_reward).ok_or(StakingError::MathOverflow)?;
+        pool.reward_liability = pool.reward_liability.checked_sub(total_reward).ok_or(StakingError::MathOverflow)?;
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.reward_vault.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
                     to: ctx.accounts.user_reward_account.to_account_info(),
                     authority: pool.to_account_info(),
                 },
-                &[&[b"staking_pool", pool.token_mint.as_ref(), &[pool.bump]]],
+                &[&pool.signer_seeds().as_seeds()],
             ),
             total_reward,
+            ctx.accounts.reward_mint.decimals,
         )?;
 
-        emit!(RewardsClaimed { user: ctx.accounts.user.key(), amount: total_reward, timestamp: clock.unix_timestamp });
+        emit!(AllRewardsClaimed {
+            user: ctx.accounts.user.key(),
+            pool: pool.key(),
+            staking_reward,
+            referral_reward,
+            total: total_reward,
+            net_total: total_reward.checked_sub(transfer_fee(&ctx.accounts.reward_mint, total_reward)?).ok_or(StakingError::MathOverflow)?,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
     pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
         let stats = &mut ctx.accounts.referrer_stats;
-        let pool = &ctx.accounts.staking_pool;
+        let pool = &mut ctx.accounts.staking_pool;
+        require!(!pool.is_paused(PAUSE_REFERRAL_CLAIM), StakingError::ReferralClaimsPaused);
+        require!(!stats.frozen, StakingError::AccountFrozen);
         
+        // With vesting on, commission must go through `vest_referral_rewards`
+        require!(pool.commission_vesting_duration == 0, StakingError::CommissionVestingEnabled);
+
+        let amount = stats.pending_rewards;
+        require!(amount > 0, StakingError::NoRewardsToClaim);
+
+        stats.pending_rewards = 0;
+        pool.reward_liability = pool.reward_liability.checked_sub(amount).ok_or(StakingError::MathOverflow)?;
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.reward_vault.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.referrer_reward_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                &[&pool.signer_seeds().as_seeds()],
+            ),
+            amount,
+            ctx.accounts.reward_mint.decimals,
+        )?;
+
+        emit!(ReferralRewardsClaimed {
+            referrer: stats.referrer,
+            pool: pool.key(),
+            amount,
+            net_amount: amount.checked_sub(transfer_fee(&ctx.accounts.reward_mint, amount)?).ok_or(StakingError::MathOverflow)?,
+            reward_liability: pool.reward_liability,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // -----------------------------------------------------
+    // COMMISSION VESTING
+    // -----------------------------------------------------
+    pub fn vest_referral_rewards(ctx: Context<VestReferralRewards>) -> Result<()> {
+        let stats = &mut ctx.accounts.referrer_stats;
+        let pool = &ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+        require!(!pool.is_paused(PAUSE_REFERRAL_CLAIM), StakingError::ReferralClaimsPaused);
+        require!(pool.commission_vesting_duration > 0, StakingError::CommissionVestingDisabled);
+        require!(!stats.flagged, StakingError::ReferrerFlagged);
+        require!(!stats.frozen, StakingError::AccountFrozen);
+
         let amount = stats.pending_rewards;
         require!(amount > 0, StakingError::NoRewardsToClaim);
 
+        let grant = &mut ctx.accounts.grant;
+        grant.referrer = stats.referrer;
+        grant.pool = pool.key();
+        grant.index = stats.grant_count;
+        grant.total_amount = amount;
+        grant.released_amount = 0;
+        grant.start_time = clock.unix_timestamp;
+        grant.cliff_time = clock.unix_timestamp.checked_add(pool.commission_vesting_cliff).ok_or(StakingError::MathOverflow)?;
+        grant.end_time = clock.unix_timestamp.checked_add(pool.commission_vesting_duration).ok_or(StakingError::MathOverflow)?;
+        grant.bump = ctx.bumps.grant;
+        grant.version = CommissionGrant::VERSION;
+
         stats.pending_rewards = 0;
+        stats.grant_count = stats.grant_count.checked_add(1).ok_or(StakingError::MathOverflow)?;
+        stats.open_grants = stats.open_grants.checked_add(1).ok_or(StakingError::MathOverflow)?;
+
+        emit!(CommissionVested {
+            referrer: grant.referrer,
+            pool: grant.pool,
+            grant: grant.key(),
+            amount,
+            cliff_time: grant.cliff_time,
+            end_time: grant.end_time,
+            pending_rewards: stats.pending_rewards,
+            grant_count: stats.grant_count,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pays out what has vested since the last release. A grant paid out in full is closed, its rent
+    /// returned to the referrer.
+    pub fn release_vested_commission(ctx: Context<ReleaseVestedCommission>) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+        let pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+        require!(!pool.is_paused(PAUSE_REFERRAL_CLAIM), StakingError::ReferralClaimsPaused);
+        require!(!ctx.accounts.referrer_stats.frozen, StakingError::AccountFrozen);
+
+        let amount = grant.vested_amount(clock.unix_timestamp).checked_sub(grant.released_amount).ok_or(StakingError::MathOverflow)?;
+        require!(amount > 0, StakingError::NoRewardsToClaim);
+        grant.released_amount = grant.released_amount.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+        pool.reward_liability = pool.reward_liability.checked_sub(amount).ok_or(StakingError::MathOverflow)?;
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.reward_vault.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
                     to: ctx.accounts.referrer_reward_account.to_account_info(),
                     authority: pool.to_account_info(),
                 },
-                &[&[b"staking_pool", pool.token_mint.as_ref(), &[pool.bump]]],
+                &[&pool.signer_seeds().as_seeds()],
             ),
             amount,
+            ctx.accounts.reward_mint.decimals,
         )?;
+
+        emit!(CommissionReleased {
+            referrer: grant.referrer,
+            pool: pool.key(),
+            grant: grant.key(),
+            amount,
+            released_amount: grant.released_amount,
+            remaining_amount: grant.total_amount.checked_sub(grant.released_amount).ok_or(StakingError::MathOverflow)?,
+            reward_liability: pool.reward_liability,
+            timestamp: clock.unix_timestamp,
+        });
+        if grant.released_amount == grant.total_amount {
+            close_finished_grant(grant, &mut ctx.accounts.referrer_stats, ctx.accounts.referrer.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    pub fn flag_referrer(ctx: Context<FlagReferrer>, flagged: bool) -> Result<()> {
+        let stats = &mut ctx.accounts.referrer_stats;
+        let old_flagged = stats.flagged;
+        stats.flagged = flagged;
+        emit!(ReferrerFlagUpdated {
+            referrer: stats.referrer,
+            pool: ctx.accounts.staking_pool.key(),
+            old_flagged,
+            new_flagged: flagged,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Freezes (or unfreezes) a compromised wallet's stake: frozen stakes can't claim, and with
+    /// `block_unstake` they can't unstake either.
+    pub fn set_user_stake_frozen(ctx: Context<FreezeUserStake>, frozen: bool, block_unstake: bool, reason: u8) -> Result<()> {
+        let user_stake = &mut ctx.accounts.user_stake;
+        user_stake.frozen = frozen;
+        user_stake.unstake_frozen = frozen && block_unstake;
+        emit_freeze(user_stake.key(), user_stake.user, user_stake.pool, frozen, reason)
+    }
+
+    /// Freezes (or unfreezes) a referrer: frozen referrers can't claim or vest commission and can't be linked to.
+    pub fn set_referrer_frozen(ctx: Context<FlagReferrer>, frozen: bool, reason: u8) -> Result<()> {
+        let stats = &mut ctx.accounts.referrer_stats;
+        stats.frozen = frozen;
+        emit_freeze(stats.key(), stats.referrer, stats.pool, frozen, reason)
+    }
+
+    /// Cuts a flagged referrer's grant down to what has vested so far; the rest stays in the reward vault.
+    /// If everything vested was already released, the grant is finished and closed.
+    pub fn clawback_commission_grant(ctx: Context<ClawbackCommissionGrant>) -> Result<()> {
+        require!(ctx.accounts.referrer_stats.flagged, StakingError::ReferrerNotFlagged);
+        let grant = &mut ctx.accounts.grant;
+        let clock = Clock::get()?;
+
+        let vested = grant.vested_amount(clock.unix_timestamp);
+        let clawed_back = grant.total_amount.checked_sub(vested).ok_or(StakingError::MathOverflow)?;
+        require!(clawed_back > 0, StakingError::NothingToClawBack);
+
+        grant.total_amount = vested;
+        grant.cliff_time = clock.unix_timestamp;
+        grant.end_time = clock.unix_timestamp;
+
+        let pool = &mut ctx.accounts.staking_pool;
+        pool.reward_liability = pool.reward_liability.checked_sub(clawed_back).ok_or(StakingError::MathOverflow)?;
+
+        emit!(CommissionClawedBack {
+            referrer: grant.referrer,
+            pool: pool.key(),
+            grant: grant.key(),
+            amount: clawed_back,
+            total_amount: grant.total_amount,
+            released_amount: grant.released_amount,
+            reward_liability: pool.reward_liability,
+            timestamp: clock.unix_timestamp,
+        });
+        if grant.released_amount == grant.total_amount {
+            close_finished_grant(grant, &mut ctx.accounts.referrer_stats, ctx.accounts.referrer.to_account_info())?;
+        }
         Ok(())
     }
 
@@ -329,7 +800,7 @@ pub mod solana_staking_referral {
     // -----------------------------------------------------
     pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
-        require!(!pool.paused, StakingError::PoolPaused);
+        require!(!pool.is_paused(PAUSE_UNSTAKE), StakingError::UnstakingPaused);
         
         // if pool.fee_unstake > 0 {
         //      invoke(
@@ -343,62 +814,162 @@ pub mod solana_staking_referral {
 
         require!(amount > 0, StakingError::ZeroAmount);
         require!(amount <= user_stake.staked_amount, StakingError::InsufficientStakedAmount);
-        require!(clock.unix_timestamp - user_stake.last_stake_time >= pool.lock_duration, StakingError::MinimumStakePeriodNotMet);
+        require!(!user_stake.unstake_frozen, StakingError::AccountFrozen);
+        // Lock is waived once the pool is sunset so everyone can exit
+        require!(pool.sunset || clock.unix_timestamp - user_stake.last_stake_time >= pool.lock_duration, StakingError::MinimumStakePeriodNotMet);
 
         update_rewards_optimized(pool, user_stake, clock.unix_timestamp)?;
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.staking_vault.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: pool.to_account_info(),
-                },
-                &[&[b"staking_pool", pool.token_mint.as_ref(), &[pool.bump]]],
-            ),
-             amount,
-        )?;
+        if pool.is_native() && ctx.accounts.user_token_account.is_none() {
+            // Native SOL pool: unwrap back to the wallet
+            let unwrap_account = ctx.accounts.native_unwrap_account.as_ref().ok_or(StakingError::MissingTokenAccount)?;
+            unwrap_native_to_user(
+                pool,
+                &ctx.accounts.staking_vault,
+                &unwrap_account.to_account_info(),
+                &ctx.accounts.token_mint,
+                &ctx.accounts.user,
+                &ctx.accounts.token_program,
+                &ctx.accounts.system_program,
+                ctx.program_id,
+                amount,
+            )?;
+        } else {
+            let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(StakingError::MissingTokenAccount)?;
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.staking_vault.to_account_info(),
+                        mint: ctx.accounts.token_mint.to_account_info(),
+                        to: user_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[&pool.signer_seeds().as_seeds()],
+                ),
+                amount,
+                ctx.accounts.token_mint.decimals,
+            )?;
+        }
 
         // Update Active Stake in Referrer Stats
         // SECURITY FIX: We verify the PDA before decrementing
-        if let Some(l1_key) = user_stake.referrer {
-            if let Some(stats) = &mut ctx.accounts.referrer_stats {
-                let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l1_key.as_ref()], ctx.program_id);
-                if stats.key() == expected_pda {
-                      stats.active_stake_l1 = stats.active_stake_l1.checked_sub(amount).unwrap_or(0);
-                }
-            }
-        }
-        
-        if let Some(l2_key) = user_stake.referrer_l2 {
-            if let Some(stats) = &mut ctx.accounts.referrer_stats_l2 {
-                let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l2_key.as_ref()], ctx.program_id);
-                if stats.key() == expected_pda {
-                      stats.active_stake_l2 = stats.active_stake_l2.checked_sub(amount).unwrap_or(0);
-                }
-            }
-        }
-
-        if let Some(l3_key) = user_stake.referrer_l3 {
-             if let Some(stats) = &mut ctx.accounts.referrer_stats_l3 {
-                let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", l3_key.as_ref()], ctx.program_id);
-                if stats.key() == expected_pda {
-                      stats.active_stake_l3 = stats.active_stake_l3.checked_sub(amount).unwrap_or(0);
-                }
-            }
-        }
+        remove_active_stake(&mut ctx.accounts.referrer_stats, &pool.key(), user_stake.referrer, 1, amount, ctx.program_id)?;
+        remove_active_stake(&mut ctx.accounts.referrer_stats_l2, &pool.key(), user_stake.referrer_l2, 2, amount, ctx.program_id)?;
+        remove_active_stake(&mut ctx.accounts.referrer_stats_l3, &pool.key(), user_stake.referrer_l3, 3, amount, ctx.program_id)?;
 
         pool.total_staked = pool.total_staked.checked_sub(amount).unwrap();
         user_stake.staked_amount = user_stake.staked_amount.checked_sub(amount).unwrap();
 
-        emit!(Unstaked { user: ctx.accounts.user.key(), amount, timestamp: clock.unix_timestamp });
+        // Principal is debited in full; a transfer-fee mint withholds part of it on the way out
+        let net_amount = amount.checked_sub(transfer_fee(&ctx.accounts.token_mint, amount)?).ok_or(StakingError::MathOverflow)?;
+        emit!(Unstaked {
+            user: ctx.accounts.user.key(),
+            pool: pool.key(),
+            amount,
+            net_amount,
+            user_staked: user_stake.staked_amount,
+            total_staked: pool.total_staked,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------
+    // EMERGENCY WITHDRAW (Principal only, ignores pause & lock)
+    // -----------------------------------------------------
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        require!(pool.emergency_mode, StakingError::EmergencyModeNotActive);
+
+        let user_stake = &mut ctx.accounts.user_stake;
+        let clock = Clock::get()?;
+        let amount = user_stake.staked_amount;
+        require!(amount > 0, StakingError::InsufficientStakedAmount);
+        require!(!user_stake.unstake_frozen, StakingError::AccountFrozen);
+        require!(user_stake.version >= UserStake::VERSION, StakingError::AccountNotMigrated);
+
+        // Keep the accumulator right for everyone else, then drop this user's rewards
+        update_pool_rewards(pool, clock.unix_timestamp);
+        let forfeited_rewards = user_stake.reward_pending;
+        user_stake.reward_pending = 0;
+        user_stake.reward_per_token_paid = pool.reward_per_token_stored;
+        pool.reward_liability = pool.reward_liability.checked_sub(forfeited_rewards).ok_or(StakingError::MathOverflow)?;
+
+        if pool.is_native() && ctx.accounts.user_token_account.is_none() {
+            let unwrap_account = ctx.accounts.native_unwrap_account.as_ref().ok_or(StakingError::MissingTokenAccount)?;
+            unwrap_native_to_user(
+                pool,
+                &ctx.accounts.staking_vault,
+                &unwrap_account.to_account_info(),
+                &ctx.accounts.token_mint,
+                &ctx.accounts.user,
+                &ctx.accounts.token_program,
+                &ctx.accounts.system_program,
+                ctx.program_id,
+                amount,
+            )?;
+        } else {
+            let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(StakingError::MissingTokenAccount)?;
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.staking_vault.to_account_info(),
+                        mint: ctx.accounts.token_mint.to_account_info(),
+                        to: user_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[&pool.signer_seeds().as_seeds()],
+                ),
+                amount,
+                ctx.accounts.token_mint.decimals,
+            )?;
+        }
+
+        remove_active_stake(&mut ctx.accounts.referrer_stats, &pool.key(), user_stake.referrer, 1, amount, ctx.program_id)?;
+        remove_active_stake(&mut ctx.accounts.referrer_stats_l2, &pool.key(), user_stake.referrer_l2, 2, amount, ctx.program_id)?;
+        remove_active_stake(&mut ctx.accounts.referrer_stats_l3, &pool.key(), user_stake.referrer_l3, 3, amount, ctx.program_id)?;
+
+        pool.total_staked = pool.total_staked.checked_sub(amount).ok_or(StakingError::MathOverflow)?;
+        user_stake.staked_amount = 0;
+
+        let net_amount = amount.checked_sub(transfer_fee(&ctx.accounts.token_mint, amount)?).ok_or(StakingError::MathOverflow)?;
+        emit!(EmergencyWithdrawn {
+            user: ctx.accounts.user.key(),
+            pool: pool.key(),
+            amount,
+            net_amount,
+            forfeited_rewards,
+            total_staked: pool.total_staked,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
+    pub fn set_emergency_mode(ctx: Context<AdminUpdate>, enabled: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        let old_enabled = pool.emergency_mode;
+        pool.emergency_mode = enabled;
+        emit!(EmergencyModeUpdated { pool: pool.key(), old_enabled, new_enabled: enabled, timestamp: Clock::get()?.unix_timestamp });
         Ok(())
     }
 
     pub fn update_fees(ctx: Context<AdminUpdate>, fee_stake: u64, fee_unstake: u64, fee_claim: u64, new_receiver: Pubkey) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
+        emit!(FeesUpdated {
+            pool: pool.key(),
+            old_fee_stake: pool.fee_stake,
+            new_fee_stake: fee_stake,
+            old_fee_unstake: pool.fee_unstake,
+            new_fee_unstake: fee_unstake,
+            old_fee_claim: pool.fee_claim,
+            new_fee_claim: fee_claim,
+            old_fee_receiver: pool.fee_receiver,
+            new_fee_receiver: new_receiver,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         pool.fee_stake = fee_stake;
         pool.fee_unstake = fee_unstake;
         pool.fee_claim = fee_claim;
@@ -409,6 +980,16 @@ pub mod solana_staking_referral {
     pub fn update_referral_rates(ctx: Context<AdminUpdate>, l1: u16, l2: u16, l3: u16) -> Result<()> {
         require!(l1.checked_add(l2).and_then(|v| v.checked_add(l3)).unwrap_or(u16::MAX) <= 2500, StakingError::ReferralRatesExceedMax);
         let pool = &mut ctx.accounts.staking_pool;
+        emit!(ReferralRatesUpdated {
+            pool: pool.key(),
+            old_l1_bps: pool.referral_l1_bps,
+            new_l1_bps: l1,
+            old_l2_bps: pool.referral_l2_bps,
+            new_l2_bps: l2,
+            old_l3_bps: pool.referral_l3_bps,
+            new_l3_bps: l3,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         pool.referral_l1_bps = l1;
         pool.referral_l2_bps = l2;
         pool.referral_l3_bps = l3;
@@ -417,6 +998,7 @@ pub mod solana_staking_referral {
 
     pub fn update_lock_duration(ctx: Context<AdminUpdate>, duration: i64) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
+        emit!(LockDurationUpdated { pool: pool.key(), old_duration: pool.lock_duration, new_duration: duration, timestamp: Clock::get()?.unix_timestamp });
         pool.lock_duration = duration;
         Ok(())
     }
@@ -424,54 +1006,282 @@ pub mod solana_staking_referral {
     pub fn update_reward_rate(ctx: Context<AdminUpdate>, new_rate: u64) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
         let clock = Clock::get()?;
-        if pool.total_staked > 0 {
-             let now = clock.unix_timestamp;
-             let delta = now.checked_sub(pool.last_update_time).unwrap_or(0);
-             if delta > 0 {
-                let reward_inc = (pool.reward_rate as u128 * delta as u128 * PRECISION) / pool.total_staked as u128;
-                pool.reward_per_token_stored += reward_inc;
-                pool.last_update_time = now;
-             }
-        } else {
-             pool.last_update_time = clock.unix_timestamp;
-        }
+        update_pool_rewards(pool, clock.unix_timestamp);
+        emit!(RewardRateUpdated {
+            pool: pool.key(),
+            old_rate: pool.reward_rate,
+            new_rate,
+            reward_per_token_stored: pool.reward_per_token_stored,
+            total_staked: pool.total_staked,
+            timestamp: clock.unix_timestamp,
+        });
         pool.reward_rate = new_rate;
         Ok(())
     }
 
-    pub fn set_pause_state(ctx: Context<AdminUpdate>, paused: bool) -> Result<()> {
+    /// Pauses or resumes every operation at once.
+    pub fn set_pause_state(ctx: Context<UpdatePoolMetadata>, paused: bool) -> Result<()> {
+        let flags = if paused { PAUSE_ALL } else { 0 };
+        apply_pause_flags(&mut ctx.accounts.staking_pool, &mut ctx.accounts.pool_entry, flags)
+    }
+
+    /// Pauses or resumes the operations in `flags` (PAUSE_* bits), leaving the others untouched.
+    pub fn set_pause_flags(ctx: Context<UpdatePoolMetadata>, flags: u8, paused: bool) -> Result<()> {
+        require!(flags & !PAUSE_ALL == 0, StakingError::InvalidPauseFlags);
+        let pool = &ctx.accounts.staking_pool;
+        let new_flags = if paused { pool.pause_flags | flags } else { pool.pause_flags & !flags };
+        apply_pause_flags(&mut ctx.accounts.staking_pool, &mut ctx.accounts.pool_entry, new_flags)
+    }
+
+    /// `start_time`/`end_time` are the pool's live schedule (`end_time == 0` = open-ended). Reopening an
+    /// ended pool accrues from now on, not for the time it was closed. The registry status follows the
+    /// pool's state; only `sunset_pool`, `close_pool` and the pause instructions change it otherwise.
+    pub fn update_pool_metadata(
+        ctx: Context<UpdatePoolMetadata>,
+        name: String,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<()> {
+        require!(name.len() <= PoolRegistryEntry::MAX_NAME_LEN, StakingError::PoolNameTooLong);
+        require!(end_time == 0 || end_time > start_time, StakingError::InvalidPoolSchedule);
+
+        let pool = &mut ctx.accounts.staking_pool;
+        require!(!pool.sunset, StakingError::PoolSunset);
+        // Settle accrual under the old schedule before changing it
+        let now = Clock::get()?.unix_timestamp;
+        update_pool_rewards(pool, now);
+        let (old_start_time, old_end_time) = (pool.start_time, pool.end_time);
+        let accumulator = Accumulator { reward_per_token_stored: pool.reward_per_token_stored, last_update_time: pool.last_update_time }
+            .reschedule(old_end_time, end_time, now);
+        pool.last_update_time = accumulator.last_update_time;
+        pool.start_time = start_time;
+        pool.end_time = end_time;
+
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        let old_status = pool_entry.status;
+        pool_entry.name = name;
+        pool_entry.start_time = start_time;
+        pool_entry.end_time = end_time;
+        pool_entry.status = pool.status(now);
+
+        emit!(PoolMetadataUpdated {
+            pool: pool.key(),
+            name: pool_entry.name.clone(),
+            old_start_time,
+            new_start_time: start_time,
+            old_end_time,
+            new_end_time: end_time,
+            old_status,
+            new_status: pool_entry.status,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Winds the pool down: stops accrual now, blocks new stakes and waives the lock for exits.
+    pub fn sunset_pool(ctx: Context<UpdatePoolMetadata>) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!pool.sunset, StakingError::PoolSunset);
+
+        update_pool_rewards(pool, now);
+        if pool.end_time == 0 || pool.end_time > now {
+            pool.end_time = now;
+        }
+        pool.sunset = true;
+
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        pool_entry.end_time = pool.end_time;
+        pool_entry.status = PoolStatus::Sunset;
+
+        emit!(PoolSunsetStarted {
+            pool: pool.key(),
+            end_time: pool.end_time,
+            total_staked: pool.total_staked,
+            reward_liability: pool.reward_liability,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Sweeps leftover vault balances to the authority and closes the vaults and the pool.
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        let pool = &ctx.accounts.staking_pool;
+        require!(pool.sunset, StakingError::PoolNotSunset);
+        require!(pool.total_staked == 0 && pool.reward_liability == 0, StakingError::PoolHasLiabilities);
+
+        let pool_signer = pool.signer_seeds();
+        let pool_seeds: &[&[u8]] = &pool_signer.as_seeds();
+        let (swept_stake_tokens, swept_reward_tokens) = (ctx.accounts.staking_vault.amount, ctx.accounts.reward_vault.amount);
+        let vaults = [
+            (&ctx.accounts.staking_vault, &ctx.accounts.token_mint, &ctx.accounts.authority_token_account, &ctx.accounts.staking_token_program),
+            (&ctx.accounts.reward_vault, &ctx.accounts.reward_mint, &ctx.accounts.authority_reward_account, &ctx.accounts.reward_token_program),
+        ];
+        for (vault, mint, destination, token_program) in vaults {
+            if vault.amount > 0 {
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: vault.to_account_info(),
+                            mint: mint.to_account_info(),
+                            to: destination.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[pool_seeds],
+                    ),
+                    vault.amount,
+                    mint.decimals,
+                )?;
+            }
+            token_interface::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: vault.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                &[pool_seeds],
+            ))?;
+        }
+
+        ctx.accounts.pool_entry.status = PoolStatus::Closed;
+        emit!(PoolClosed {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            swept_stake_tokens,
+            swept_reward_tokens,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the per-wallet and pool-wide stake caps (0 = unlimited).
+    pub fn update_stake_limits(ctx: Context<AdminUpdate>, max_stake_per_wallet: u64, max_total_staked: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        emit!(StakeLimitsUpdated {
+            pool: pool.key(),
+            old_max_stake_per_wallet: pool.max_stake_per_wallet,
+            new_max_stake_per_wallet: max_stake_per_wallet,
+            old_max_total_staked: pool.max_total_staked,
+            new_max_total_staked: max_total_staked,
+            total_staked: pool.total_staked,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        pool.max_stake_per_wallet = max_stake_per_wallet;
+        pool.max_total_staked = max_total_staked;
+        Ok(())
+    }
+
+    /// Gates stakes behind a Merkle allowlist (all-zero root = open pool).
+    pub fn set_allowlist_root(ctx: Context<AdminUpdate>, root: [u8; 32]) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        emit!(AllowlistRootUpdated { pool: pool.key(), old_root: pool.allowlist_root, new_root: root, timestamp: Clock::get()?.unix_timestamp });
+        pool.allowlist_root = root;
+        Ok(())
+    }
+
+    pub fn set_restake_pool(ctx: Context<AdminUpdate>, restake_pool: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        // Restaking into this pool is RestakeThisPool; as a target it would alias the source accounts
+        require_keys_neq!(restake_pool, pool.key(), StakingError::InvalidRestakeTarget);
+        emit!(RestakePoolUpdated { pool: pool.key(), old_restake_pool: pool.restake_pool, new_restake_pool: restake_pool, timestamp: Clock::get()?.unix_timestamp });
+        pool.restake_pool = restake_pool;
+        Ok(())
+    }
+
+    pub fn update_commission_vesting(ctx: Context<AdminUpdate>, cliff: i64, duration: i64) -> Result<()> {
+        require!(duration >= 0 && cliff >= 0 && cliff <= duration, StakingError::InvalidVestingSchedule);
         let pool = &mut ctx.accounts.staking_pool;
-        pool.paused = paused;
+        emit!(CommissionVestingUpdated {
+            pool: pool.key(),
+            old_cliff: pool.commission_vesting_cliff,
+            new_cliff: cliff,
+            old_duration: pool.commission_vesting_duration,
+            new_duration: duration,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        pool.commission_vesting_cliff = cliff;
+        pool.commission_vesting_duration = duration;
+        Ok(())
+    }
+
+    pub fn update_referral_guards(ctx: Context<AdminUpdate>, min_referee_stake: u64, min_referrer_stake_age: i64) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        emit!(ReferralGuardsUpdated {
+            pool: pool.key(),
+            old_min_referee_stake: pool.min_referee_stake,
+            new_min_referee_stake: min_referee_stake,
+            old_min_referrer_stake_age: pool.min_referrer_stake_age,
+            new_min_referrer_stake_age: min_referrer_stake_age,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        pool.min_referee_stake = min_referee_stake;
+        pool.min_referrer_stake_age = min_referrer_stake_age;
         Ok(())
     }
 
     pub fn toggle_referral_system(ctx: Context<AdminUpdate>, enabled: bool) -> Result<()> {
         let pool = &mut ctx.accounts.staking_pool;
+        emit!(ReferralSystemToggled { pool: pool.key(), old_enabled: pool.referral_enabled, new_enabled: enabled, timestamp: Clock::get()?.unix_timestamp });
         pool.referral_enabled = enabled;
         Ok(())
     }
 
+    /// In strict mode, stakes and claims fail unless every upline's ReferrerStats is passed in.
+    /// Unstake and emergency withdraw are exempt: they skip missing accounts either way.
+    pub fn set_strict_referrer_accounts(ctx: Context<AdminUpdate>, enabled: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.staking_pool;
+        emit!(StrictReferrerAccountsUpdated {
+            pool: pool.key(),
+            old_enabled: pool.strict_referrer_accounts,
+            new_enabled: enabled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        pool.strict_referrer_accounts = enabled;
+        Ok(())
+    }
+
+    /// Only the surplus is withdrawable: the vault balance above staked principal and owed rewards.
     pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, amount: u64) -> Result<()> {
         let pool = &ctx.accounts.staking_pool;
         require!(amount > 0, StakingError::ZeroAmount);
-         token::transfer(
+        let surplus = ctx.accounts.staking_vault.amount
+            .saturating_sub(pool.total_staked)
+            .saturating_sub(pool.reward_liability);
+        require!(amount <= surplus, StakingError::WithdrawExceedsSurplus);
+         token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.staking_vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
                     to: ctx.accounts.admin_token_account.to_account_info(),
                     authority: ctx.accounts.staking_pool.to_account_info(),
                 },
-                &[&[b"staking_pool", pool.token_mint.as_ref(), &[pool.bump]]]
+                &[&pool.signer_seeds().as_seeds()]
             ),
-            amount
+            amount,
+            ctx.accounts.token_mint.decimals
         )?;
+
+        ctx.accounts.staking_vault.reload()?;
+        emit!(TokensWithdrawn {
+            pool: ctx.accounts.staking_pool.key(),
+            authority: ctx.accounts.authority.key(),
+            destination: ctx.accounts.admin_token_account.key(),
+            amount,
+            vault_balance: ctx.accounts.staking_vault.amount,
+            total_staked: ctx.accounts.staking_pool.total_staked,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
     pub fn initialize_referrer_stats(ctx: Context<InitializeReferrerStats>) -> Result<()> {
         let stats = &mut ctx.accounts.referrer_stats;
         stats.referrer = ctx.accounts.referrer.key();
+        stats.pool = ctx.accounts.staking_pool.key();
         stats.total_commission_earned = 0;
         stats.pending_rewards = 0;
         stats.total_referrals = 0; 
@@ -479,77 +1289,1091 @@ pub mod solana_staking_referral {
         stats.active_stake_l1 = 0; // Init new fields
         stats.active_stake_l2 = 0;
         stats.active_stake_l3 = 0;
+        stats.grant_count = 0;
+        stats.open_grants = 0;
+        stats.flagged = false;
+        stats.frozen = false;
+        stats.version = ReferrerStats::VERSION;
+        emit!(ReferrerStatsInitialized { referrer: stats.referrer, pool: stats.pool, referrer_stats: stats.key(), timestamp: Clock::get()?.unix_timestamp });
         Ok(())
     }
     
     pub fn add_rewards(ctx: Context<AddRewards>, amount: u64) -> Result<()> {
-         token::transfer(
+         token_interface::transfer_checked(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.funder_reward_account.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
                     to: ctx.accounts.reward_vault.to_account_info(),
                     authority: ctx.accounts.funder.to_account_info(),
                 }
             ),
-            amount
+            amount,
+            ctx.accounts.reward_mint.decimals
          )?;
+
+         ctx.accounts.reward_vault.reload()?;
+         emit!(RewardsAdded {
+             pool: ctx.accounts.staking_pool.key(),
+             funder: ctx.accounts.funder.key(),
+             amount,
+             reward_vault_balance: ctx.accounts.reward_vault.amount,
+             reward_liability: ctx.accounts.staking_pool.reward_liability,
+             timestamp: Clock::get()?.unix_timestamp,
+         });
          Ok(())
     }
-}
 
-// -----------------------------------------------------
-// HELPERS & ACCOUNTS
-// -----------------------------------------------------
-fn update_rewards_optimized(pool: &mut StakingPool, user_stake: &mut UserStake, now: i64) -> Result<()> {
-    if pool.total_staked == 0 {
-        pool.last_update_time = now;
-        return Ok(());
+    /// Returns an emptied stake's rent to the user. Downline chains store referrer keys, not this account,
+    /// so closing it doesn't break them; frozen stakes stay open so the freeze can't be reset.
+    /// Returns an empty stake's rent. This ends its referral link on purpose: nothing is owed on an
+    /// empty stake, and a later stake in the pool starts over as a first stake, free to link any
+    /// referrer that passes the usual guards.
+    pub fn close_user_stake(ctx: Context<CloseUserStake>) -> Result<()> {
+        let user_stake = &ctx.accounts.user_stake;
+        require!(user_stake.staked_amount == 0 && user_stake.reward_pending == 0, StakingError::StakeNotEmpty);
+        require!(!user_stake.frozen, StakingError::AccountFrozen);
+        emit!(UserStakeClosed { user: user_stake.user, pool: user_stake.pool, user_stake: user_stake.key(), timestamp: Clock::get()?.unix_timestamp });
+        Ok(())
     }
-    let delta = now.checked_sub(pool.last_update_time).unwrap_or(0);
-    if delta > 0 {
-        let reward_inc = (pool.reward_rate as u128 * delta as u128 * PRECISION) / pool.total_staked as u128;
-        pool.reward_per_token_stored += reward_inc;
-        pool.last_update_time = now;
+
+    /// Returns a referrer's stats rent once nothing depends on it: no pending commission, no downline
+    /// stake still counted against it and no grant still vesting or unreleased. Finished grants are
+    /// closed as they finish, so reopened stats can number new grants from 0 again.
+    pub fn close_referrer_stats(ctx: Context<CloseReferrerStats>) -> Result<()> {
+        let stats = &ctx.accounts.referrer_stats;
+        require!(stats.pending_rewards == 0, StakingError::ReferrerStatsInUse);
+        require!(stats.active_stake_l1 == 0 && stats.active_stake_l2 == 0 && stats.active_stake_l3 == 0, StakingError::ReferrerStatsInUse);
+        require!(stats.open_grants == 0, StakingError::ReferrerStatsInUse);
+        require!(!stats.frozen && !stats.flagged, StakingError::AccountFrozen);
+        emit!(ReferrerStatsClosed {
+            referrer: stats.referrer,
+            pool: stats.pool,
+            referrer_stats: stats.key(),
+            total_referrals: stats.total_referrals,
+            total_commission_earned: stats.total_commission_earned,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
     }
-    if user_stake.staked_amount > 0 {
-        let pending = (user_stake.staked_amount as u128 * (pool.reward_per_token_stored - user_stake.reward_per_token_paid)) / PRECISION;
-        user_stake.reward_pending = user_stake.reward_pending.checked_add(pending as u64).unwrap();
+
+    // -----------------------------------------------------
+    // VIEWS (read-only; call through simulateTransaction, no signer needed)
+    // -----------------------------------------------------
+    /// Claimable staking reward as of now, settled on a copy so nothing is written.
+    pub fn get_pending_rewards(ctx: Context<ViewUserStake>) -> Result<u64> {
+        let mut pool = (*ctx.accounts.staking_pool).clone();
+        let mut user_stake = (*ctx.accounts.user_stake).clone();
+        update_rewards_optimized(&mut pool, &mut user_stake, Clock::get()?.unix_timestamp)?;
+        Ok(user_stake.reward_pending)
     }
-    user_stake.reward_per_token_paid = pool.reward_per_token_stored;
-    Ok(())
-}
 
-#[account]
-pub struct StakingPool {
-    pub authority: Pubkey,
-    pub token_mint: Pubkey,
-    pub reward_mint: Pubkey,
-    pub staking_vault: Pubkey,
-    pub reward_vault: Pubkey,
-    pub fee_receiver: Pubkey,
-    pub reward_per_token_stored: u128,
-    pub reward_rate: u64,
-    pub total_staked: u64,
-    pub fee_stake: u64,
-    pub fee_unstake: u64,
-    pub fee_claim: u64,
-    pub min_stake_amount: u64,
-    pub last_update_time: i64,
-    pub lock_duration: i64,
-    pub referral_l1_bps: u16,
+    pub fn get_pool_state(ctx: Context<ViewPool>) -> Result<PoolState> {
+        let mut pool = (*ctx.accounts.staking_pool).clone();
+        let now = Clock::get()?.unix_timestamp;
+        update_pool_rewards(&mut pool, now);
+        Ok(PoolState {
+            total_staked: pool.total_staked,
+            reward_rate: pool.reward_rate,
+            reward_per_token_stored: pool.reward_per_token_stored,
+            reward_vault_balance: ctx.accounts.reward_vault.amount,
+            reward_liability: pool.reward_liability,
+            start_time: pool.start_time,
+            end_time: pool.end_time,
+            lock_duration: pool.lock_duration,
+            pause_flags: pool.pause_flags,
+            sunset: pool.sunset,
+            emergency_mode: pool.emergency_mode,
+            accepting_stakes: pool.pause_flags & PAUSE_STAKE == 0 && pool.check_accepting_stakes(now).is_ok(),
+            timestamp: now,
+        })
+    }
+
+    /// Earliest time `unstake` accepts this stake; sunset pools waive the lock, so that's now.
+    pub fn get_unlock_time(ctx: Context<ViewUserStake>) -> Result<i64> {
+        let pool = &ctx.accounts.staking_pool;
+        let now = Clock::get()?.unix_timestamp;
+        if pool.sunset {
+            return Ok(now);
+        }
+        reward_math::unlock_time(ctx.accounts.user_stake.last_stake_time, pool.lock_duration).ok_or(error!(StakingError::MathOverflow))
+    }
+
+    pub fn get_referrer_summary(ctx: Context<ViewReferrer>) -> Result<ReferrerSummary> {
+        let stats = &ctx.accounts.referrer_stats;
+        Ok(ReferrerSummary {
+            referrer: stats.referrer,
+            pool: stats.pool,
+            total_referrals: stats.total_referrals,
+            pending_rewards: stats.pending_rewards,
+            total_commission_earned: stats.total_commission_earned,
+            volume_referred: stats.volume_referred,
+            active_stake_l1: stats.active_stake_l1,
+            active_stake_l2: stats.active_stake_l2,
+            active_stake_l3: stats.active_stake_l3,
+            grant_count: stats.grant_count,
+            flagged: stats.flagged,
+            frozen: stats.frozen,
+        })
+    }
+
+    // -----------------------------------------------------
+    // ACCOUNT MIGRATIONS (permissionless except referrer stats; payer tops up rent)
+    // -----------------------------------------------------
+    /// Rewrites a pool from before pool ids (`StakingPoolV0`) into the current layout and lists it in the
+    /// registry under the next pool id. Fields were inserted mid-struct since, so the old bytes are decoded
+    /// explicitly rather than zero-extended; the pool keeps its `["staking_pool", token_mint]` address.
+    pub fn migrate_staking_pool(ctx: Context<MigrateStakingPool>) -> Result<()> {
+        let account = ctx.accounts.account.to_account_info();
+        require_keys_eq!(*account.owner, *ctx.program_id, StakingError::InvalidMigrationAccount);
+        let old_pool = {
+            let data = account.try_borrow_data()?;
+            require!(data.len() >= 8 && data[..8] == StakingPool::DISCRIMINATOR[..], StakingError::InvalidMigrationAccount);
+            require!(data.len() == 8 + StakingPoolV0::SIZE, StakingError::AlreadyMigrated);
+            StakingPoolV0::deserialize(&mut &data[8..])?
+        };
+        let (legacy_address, _) = Pubkey::find_program_address(&[b"staking_pool", old_pool.token_mint.as_ref()], ctx.program_id);
+        require_keys_eq!(account.key(), legacy_address, StakingError::InvalidMigrationAccount);
+
+        let registry = &mut ctx.accounts.registry;
+        let pool_id = registry.pool_count;
+        registry.pool_count = registry.pool_count.checked_add(1).ok_or(StakingError::MathOverflow)?;
+        let pool = old_pool.into_current(pool_id);
+
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        pool_entry.pool = account.key();
+        pool_entry.pool_id = pool_id;
+        pool_entry.token_mint = pool.token_mint;
+        pool_entry.reward_mint = pool.reward_mint;
+        pool_entry.name = String::new();
+        pool_entry.start_time = pool.start_time;
+        pool_entry.end_time = pool.end_time;
+        pool_entry.status = pool.status(Clock::get()?.unix_timestamp);
+        pool_entry.bump = ctx.bumps.pool_entry;
+        pool_entry.version = PoolRegistryEntry::VERSION;
+
+        realloc_for_migration(&account, &StakingPool::DISCRIMINATOR, 8 + StakingPool::SIZE, &ctx.accounts.payer, &ctx.accounts.system_program, ctx.program_id)?;
+        pool.try_serialize(&mut &mut account.data.borrow_mut()[..])?;
+        emit_migrated(&account, account.key(), 0, pool.version)
+    }
+
+    /// Appends the fields added since the stake was created and books its settled reward as pool
+    /// liability (V0 pools didn't track it). The pool must be migrated first.
+    pub fn migrate_user_stake(ctx: Context<MigrateUserStake>) -> Result<()> {
+        let account = ctx.accounts.account.to_account_info();
+        realloc_for_migration(&account, &UserStake::DISCRIMINATOR, 8 + UserStake::SIZE, &ctx.accounts.payer, &ctx.accounts.system_program, ctx.program_id)?;
+        let mut user_stake = UserStake::try_deserialize(&mut &account.data.borrow()[..])?;
+        require!(user_stake.version < UserStake::VERSION, StakingError::AlreadyMigrated);
+        let pool = &mut ctx.accounts.staking_pool;
+        require_keys_eq!(user_stake.pool, pool.key(), StakingError::InvalidUserStakePool);
+
+        pool.reward_liability = pool.reward_liability.checked_add(user_stake.reward_pending).ok_or(StakingError::MathOverflow)?;
+        // The real first stake time wasn't recorded; the last stake is the latest it can have been
+        if user_stake.first_stake_time == 0 && user_stake.staked_amount > 0 {
+            user_stake.first_stake_time = user_stake.last_stake_time;
+        }
+        let old_version = user_stake.version;
+        user_stake.version = UserStake::VERSION;
+        user_stake.try_serialize(&mut &mut account.data.borrow_mut()[..])?;
+        emit_migrated(&account, user_stake.pool, old_version, user_stake.version)
+    }
+
+    /// Stats from before per-pool commission live at `["referrer_stats", referrer]` with `pool` unset, so no
+    /// pool's instructions accept them. Only legacy pools can have earned that commission, so the pool's
+    /// authority picks which one owes it: the counters are added to the referrer's stats in that pool (created
+    /// if needed), the pending commission is booked as its liability and the old account is closed to the referrer.
+    pub fn migrate_referrer_stats(ctx: Context<MigrateReferrerStats>) -> Result<()> {
+        let legacy = ctx.accounts.legacy_stats.to_account_info();
+        let referrer = ctx.accounts.referrer.key();
+        let (legacy_address, _) = Pubkey::find_program_address(&[b"referrer_stats", referrer.as_ref()], ctx.program_id);
+        require_keys_eq!(legacy.key(), legacy_address, StakingError::InvalidMigrationAccount);
+        require!(legacy.lamports() > 0, StakingError::AlreadyMigrated);
+        require_keys_eq!(*legacy.owner, *ctx.program_id, StakingError::InvalidMigrationAccount);
+        // Accounts already grown in place by an earlier version of this instruction share the V0 prefix
+        let old_stats = {
+            let data = legacy.try_borrow_data()?;
+            require!(data.len() >= 8 + ReferrerStatsV0::SIZE && data[..8] == ReferrerStats::DISCRIMINATOR[..], StakingError::InvalidMigrationAccount);
+            ReferrerStatsV0::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(old_stats.referrer, referrer, StakingError::InvalidMigrationAccount);
+
+        let pool = &mut ctx.accounts.staking_pool;
+        pool.reward_liability = pool.reward_liability.checked_add(old_stats.pending_rewards).ok_or(StakingError::MathOverflow)?;
+
+        let stats = &mut ctx.accounts.referrer_stats;
+        stats.referrer = referrer;
+        stats.pool = pool.key();
+        stats.version = ReferrerStats::VERSION;
+        stats.total_referrals = stats.total_referrals.checked_add(old_stats.total_referrals).ok_or(StakingError::MathOverflow)?;
+        stats.total_commission_earned = stats.total_commission_earned.checked_add(old_stats.total_commission_earned).ok_or(StakingError::MathOverflow)?;
+        stats.pending_rewards = stats.pending_rewards.checked_add(old_stats.pending_rewards).ok_or(StakingError::MathOverflow)?;
+        stats.volume_referred = stats.volume_referred.checked_add(old_stats.volume_referred).ok_or(StakingError::MathOverflow)?;
+        stats.active_stake_l1 = stats.active_stake_l1.checked_add(old_stats.active_stake_l1).ok_or(StakingError::MathOverflow)?;
+        stats.active_stake_l2 = stats.active_stake_l2.checked_add(old_stats.active_stake_l2).ok_or(StakingError::MathOverflow)?;
+        stats.active_stake_l3 = stats.active_stake_l3.checked_add(old_stats.active_stake_l3).ok_or(StakingError::MathOverflow)?;
+
+        let referrer_info = ctx.accounts.referrer.to_account_info();
+        let rent = legacy.lamports();
+        **referrer_info.try_borrow_mut_lamports()? = referrer_info.lamports().checked_add(rent).ok_or(StakingError::MathOverflow)?;
+        **legacy.try_borrow_mut_lamports()? = 0;
+        legacy.assign(&system_program::ID);
+        legacy.realloc(0, false)?;
+
+        emit_migrated(&stats.to_account_info(), stats.pool, 0, stats.version)
+    }
+
+    // -----------------------------------------------------
+    // REFERRAL COMPETITIONS
+    // -----------------------------------------------------
+    pub fn create_competition(
+        ctx: Context<CreateCompetition>,
+        round_id: u64,
+        start_time: i64,
+        end_time: i64,
+        prize_split_bps: Vec<u16>,
+    ) -> Result<()> {
+        require!(end_time > start_time, StakingError::InvalidCompetitionWindow);
+        require!(!prize_split_bps.is_empty() && prize_split_bps.len() <= ReferralCompetition::MAX_WINNERS, StakingError::InvalidPrizeSplit);
+        let split_total = prize_split_bps.iter().try_fold(0u16, |acc, bps| acc.checked_add(*bps)).unwrap_or(u16::MAX);
+        require!(split_total <= BASIS_POINTS, StakingError::InvalidPrizeSplit);
+        // One round at a time, so stakes always know which round their volume belongs to
+        let pool = &mut ctx.accounts.staking_pool;
+        require_keys_eq!(pool.active_competition, Pubkey::default(), StakingError::CompetitionAlreadyActive);
+
+        let competition = &mut ctx.accounts.competition;
+        pool.active_competition = competition.key();
+        competition.pool = pool.key();
+        competition.round_id = round_id;
+        competition.start_time = start_time;
+        competition.end_time = end_time;
+        competition.prize_vault = ctx.accounts.prize_vault.key();
+        competition.prize_split_bps = prize_split_bps;
+        competition.leaderboard = Vec::new();
+        competition.total_volume = 0;
+        competition.settled = false;
+        competition.bump = ctx.bumps.competition;
+        competition.version = ReferralCompetition::VERSION;
+
+        emit!(CompetitionCreated {
+            competition: competition.key(),
+            pool: competition.pool,
+            round_id,
+            start_time,
+            end_time,
+            prize_split_bps: competition.prize_split_bps.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn fund_competition(ctx: Context<FundCompetition>, amount: u64) -> Result<()> {
+        require!(!ctx.accounts.competition.settled, StakingError::CompetitionAlreadySettled);
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.funder_reward_account.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.prize_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                }
+            ),
+            amount,
+            ctx.accounts.reward_mint.decimals
+        )?;
+
+        ctx.accounts.prize_vault.reload()?;
+        emit!(CompetitionFunded {
+            competition: ctx.accounts.competition.key(),
+            funder: ctx.accounts.funder.key(),
+            amount,
+            prize_pool: ctx.accounts.prize_vault.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Opens the referrer's volume tally for one round; only entered referrers rank on the leaderboard.
+    pub fn enter_competition(ctx: Context<EnterCompetition>) -> Result<()> {
+        require!(!ctx.accounts.competition.settled, StakingError::CompetitionAlreadySettled);
+        let entry = &mut ctx.accounts.competition_entry;
+        entry.competition = ctx.accounts.competition.key();
+        entry.referrer = ctx.accounts.referrer.key();
+        entry.volume = 0;
+        entry.bump = ctx.bumps.competition_entry;
+        entry.version = CompetitionEntry::VERSION;
+
+        emit!(CompetitionEntered {
+            competition: entry.competition,
+            referrer: entry.referrer,
+            competition_entry: entry.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pays the prize pool out by `prize_split_bps` in leaderboard order.
+    /// `remaining_accounts` must hold one reward token account per ranked referrer, in rank order.
+    pub fn settle_competition<'info>(ctx: Context<'_, '_, '_, 'info, SettleCompetition<'info>>) -> Result<()> {
+        let competition = &mut ctx.accounts.competition;
+        let clock = Clock::get()?;
+        require!(!competition.settled, StakingError::CompetitionAlreadySettled);
+        require!(clock.unix_timestamp >= competition.end_time, StakingError::CompetitionNotEnded);
+        require!(ctx.remaining_accounts.len() == competition.leaderboard.len(), StakingError::InvalidWinnerAccount);
+
+        let prize_pool = ctx.accounts.prize_vault.amount;
+        let competition_key = competition.key();
+        let competition_info = competition.to_account_info();
+        let round_bytes = competition.round_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"referral_competition", competition.pool.as_ref(), &round_bytes, &[competition.bump]];
+
+        for (rank, (entry, winner_info)) in competition.leaderboard.iter().zip(ctx.remaining_accounts.iter()).enumerate() {
+            let winner_account = InterfaceAccount::<TokenAccount>::try_from(winner_info)?;
+            require!(
+                winner_account.owner == entry.referrer && winner_account.mint == ctx.accounts.prize_vault.mint,
+                StakingError::InvalidWinnerAccount
+            );
+
+            let prize = reward_math::bps_share(prize_pool, competition.prize_split_bps[rank]);
+            if prize == 0 {
+                continue;
+            }
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.prize_vault.to_account_info(),
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: winner_info.clone(),
+                        authority: competition_info.clone(),
+                    },
+                    &[signer_seeds],
+                ),
+                prize,
+                ctx.accounts.reward_mint.decimals,
+            )?;
+            emit!(CompetitionPrizePaid {
+                competition: competition_key,
+                referrer: entry.referrer,
+                rank: rank as u8,
+                volume: entry.volume,
+                amount: prize,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        competition.settled = true;
+        ctx.accounts.staking_pool.active_competition = Pubkey::default();
+        emit!(CompetitionSettled {
+            competition: competition_key,
+            pool: competition.pool,
+            prize_pool,
+            winners: competition.leaderboard.len() as u8,
+            total_volume: competition.total_volume,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Returns what settlement left in the prize vault (splits under 100% or unfilled ranks) to the authority.
+    pub fn sweep_competition_remainder(ctx: Context<SweepCompetitionRemainder>) -> Result<()> {
+        let competition = &ctx.accounts.competition;
+        require!(competition.settled, StakingError::CompetitionNotSettled);
+        let amount = ctx.accounts.prize_vault.amount;
+        require!(amount > 0, StakingError::ZeroAmount);
+
+        let round_bytes = competition.round_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"referral_competition", competition.pool.as_ref(), &round_bytes, &[competition.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.prize_vault.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: competition.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            amount,
+            ctx.accounts.reward_mint.decimals,
+        )?;
+
+        emit!(CompetitionRemainderSwept {
+            competition: competition.key(),
+            pool: competition.pool,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------
+// HELPERS & ACCOUNTS
+// -----------------------------------------------------
+/// Advances the pool accumulator to `now`; nothing accrues past `end_time`.
+fn update_pool_rewards(pool: &mut StakingPool, now: i64) {
+    let accumulator = Accumulator {
+        reward_per_token_stored: pool.reward_per_token_stored,
+        last_update_time: pool.last_update_time,
+    }
+    .update(pool.reward_rate, pool.total_staked, pool.end_time, now);
+    pool.reward_per_token_stored = accumulator.reward_per_token_stored;
+    pool.last_update_time = accumulator.last_update_time;
+}
+
+fn update_rewards_optimized(pool: &mut StakingPool, user_stake: &mut UserStake, now: i64) -> Result<()> {
+    // Unmigrated stakes hold reward that was never booked as liability
+    require!(user_stake.version >= UserStake::VERSION, StakingError::AccountNotMigrated);
+    update_pool_rewards(pool, now);
+    if user_stake.staked_amount > 0 {
+        let pending = reward_math::pending_reward(user_stake.staked_amount, pool.reward_per_token_stored, user_stake.reward_per_token_paid);
+        user_stake.reward_pending = user_stake.reward_pending.checked_add(pending).unwrap();
+        pool.reward_liability = pool.reward_liability.checked_add(pending).ok_or(StakingError::MathOverflow)?;
+    }
+    user_stake.reward_per_token_paid = pool.reward_per_token_stored;
+    Ok(())
+}
+
+/// Rejects Token-2022 mints whose extensions would break vault accounting.
+fn validate_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(()); // Legacy SPL mints have no extensions
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    // Extensions newer than the token-2022 version we build against (e.g. Pausable) don't parse; reject them too
+    let extensions = mint_state.get_extension_types().map_err(|_| error!(StakingError::UnsupportedMintExtension))?;
+    for extension in extensions {
+        match extension {
+            // Tokens can't leave the vault, balances are hidden, or a delegate can drain the vault
+            ExtensionType::NonTransferable
+            | ExtensionType::ConfidentialTransferMint
+            | ExtensionType::ConfidentialTransferFeeConfig
+            | ExtensionType::PermanentDelegate
+            // Vaults and user accounts could be created frozen, or the mint closed under the pool
+            | ExtensionType::DefaultAccountState
+            | ExtensionType::MintCloseAuthority
+            // Transfers would need extra accounts we don't pass
+            | ExtensionType::TransferHook => return err!(StakingError::UnsupportedMintExtension),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Fee a Token-2022 mint withholds when `amount` is transferred this epoch (zero for mints without a transfer fee).
+fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => Ok(fee_config.calculate_epoch_fee(Clock::get()?.epoch, amount).ok_or(StakingError::MathOverflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Grows a pre-versioning account to `new_len`, topping up rent from `payer`. The new tail is zeroed,
+/// which is the default for every field appended since (including `version` = 0 until the caller bumps it).
+fn realloc_for_migration<'info>(
+    account: &AccountInfo<'info>,
+    discriminator: &[u8],
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(*account.owner, *program_id, StakingError::InvalidMigrationAccount);
+    require!(account.data_len() >= 8 && account.data.borrow()[..8] == discriminator[..], StakingError::InvalidMigrationAccount);
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+
+    let rent_due = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer { from: payer.to_account_info(), to: account.clone() },
+            ),
+            rent_due,
+        )?;
+    }
+    account.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// Pays `amount` of wrapped SOL out of a pool vault as plain lamports: moves it into a temporary
+/// PDA token account (`["native_unwrap", user]`) owned by the pool, then closes that account to the user.
+#[allow(clippy::too_many_arguments)]
+fn unwrap_native_to_user<'info>(
+    pool: &Account<'info, StakingPool>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    unwrap_account: &AccountInfo<'info>,
+    native_mint: &InterfaceAccount<'info, Mint>,
+    user: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let user_key = user.key();
+    let (expected_unwrap, unwrap_bump) = Pubkey::find_program_address(&[b"native_unwrap", user_key.as_ref()], program_id);
+    require!(unwrap_account.key() == expected_unwrap, StakingError::InvalidNativeUnwrapAccount);
+    let unwrap_seeds: &[&[u8]] = &[b"native_unwrap", user_key.as_ref(), &[unwrap_bump]];
+    let pool_signer = pool.signer_seeds();
+    let pool_seeds: &[&[u8]] = &pool_signer.as_seeds();
+
+    // User fronts the rent; it comes back when the account is closed. Allocate + assign instead of
+    // create_account, which fails if anyone has sent lamports to the (predictable) address first.
+    let rent = Rent::get()?.minimum_balance(spl_token::state::Account::LEN);
+    let top_up = rent.saturating_sub(unwrap_account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer { from: user.to_account_info(), to: unwrap_account.clone() },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate { account_to_allocate: unwrap_account.clone() },
+            &[unwrap_seeds],
+        ),
+        spl_token::state::Account::LEN as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign { account_to_assign: unwrap_account.clone() },
+            &[unwrap_seeds],
+        ),
+        &token_program.key(),
+    )?;
+    token_interface::initialize_account3(CpiContext::new(
+        token_program.to_account_info(),
+        InitializeAccount3 {
+            account: unwrap_account.clone(),
+            mint: native_mint.to_account_info(),
+            authority: pool.to_account_info(),
+        },
+    ))?;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: native_mint.to_account_info(),
+                to: unwrap_account.clone(),
+                authority: pool.to_account_info(),
+            },
+            &[pool_seeds],
+        ),
+        amount,
+        native_mint.decimals,
+    )?;
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: unwrap_account.clone(),
+            destination: user.to_account_info(),
+            authority: pool.to_account_info(),
+        },
+        &[pool_seeds],
+    ))?;
+    Ok(())
+}
+
+/// Admits `user_stake` to an allowlist-gated pool: a valid proof is remembered (root and cap) on the stake,
+/// so later top-ups and restakes against the same root don't need one.
+fn check_allowlist(pool: &StakingPool, user_stake: &mut UserStake, proof: Option<AllowlistProof>) -> Result<()> {
+    if pool.allowlist_root == [0u8; 32] {
+        return Ok(());
+    }
+    match proof {
+        Some(proof) => {
+            let leaf = merkle::leaf(&user_stake.user, proof.cap);
+            require!(merkle::verify(&proof.proof, &pool.allowlist_root, leaf), StakingError::InvalidAllowlistProof);
+            user_stake.allowlist_root = pool.allowlist_root;
+            user_stake.allowlist_cap = proof.cap;
+        }
+        None => require!(user_stake.allowlist_root == pool.allowlist_root, StakingError::AllowlistProofRequired),
+    }
+    Ok(())
+}
+
+/// Adds `amount` to a stake: enforces stake caps, blends the lock timer by weight and updates pool and user totals.
+/// Rewards must already be settled via `update_rewards_optimized`.
+fn apply_stake(pool: &mut StakingPool, user_stake: &mut UserStake, amount: u64, now: i64) -> Result<()> {
+    // Caps (0 = unlimited) apply to every path that adds stake, including restakes
+    let new_total_staked = pool.total_staked.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+    let new_user_staked = user_stake.staked_amount.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+    require!(pool.max_total_staked == 0 || new_total_staked <= pool.max_total_staked, StakingError::PoolCapacityExceeded);
+    require!(pool.max_stake_per_wallet == 0 || new_user_staked <= pool.max_stake_per_wallet, StakingError::WalletStakeCapExceeded);
+    require!(user_stake.allowlist_cap == 0 || new_user_staked <= user_stake.allowlist_cap, StakingError::WalletStakeCapExceeded);
+
+    pool.total_staked = new_total_staked;
+    
+    // Lock Timer Logic
+    let new_last_stake_time =
+        reward_math::weighted_last_stake_time(user_stake.staked_amount, user_stake.last_stake_time, amount, pool.lock_duration, now);
+
+    if user_stake.staked_amount == 0 {
+        user_stake.first_stake_time = now;
+    }
+    user_stake.staked_amount = new_user_staked;
+    user_stake.last_stake_time = new_last_stake_time;
+    Ok(())
+}
+
+/// Adds `amount` to the active stake a referrer sees at `level` (1-3), if the supplied stats account is theirs in `pool`.
+fn add_active_stake(
+    stats: &mut Option<Account<'_, ReferrerStats>>,
+    pool: &Pubkey,
+    referrer: Option<Pubkey>,
+    level: u8,
+    amount: u64,
+    strict: bool,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if !referrer_stats_matches(stats, pool, referrer, strict, program_id)? {
+        return Ok(());
+    }
+    if let Some(stats) = stats {
+        let active = match level {
+            1 => &mut stats.active_stake_l1,
+            2 => &mut stats.active_stake_l2,
+            _ => &mut stats.active_stake_l3,
+        };
+        *active = active.checked_add(amount).ok_or(StakingError::MathOverflow)?;
+    }
+    Ok(())
+}
+
+/// Whether `stats` is `referrer`'s stats account in `pool`. Without strict mode a missing or foreign account
+/// is skipped; with it, any referrer that has no matching account fails with `InvalidReferrerAccount`.
+fn referrer_stats_matches(
+    stats: &Option<Account<'_, ReferrerStats>>,
+    pool: &Pubkey,
+    referrer: Option<Pubkey>,
+    strict: bool,
+    program_id: &Pubkey,
+) -> Result<bool> {
+    let Some(referrer_key) = referrer else {
+        return Ok(false);
+    };
+    let (expected_pda, _) = Pubkey::find_program_address(&[b"referrer_stats", pool.as_ref(), referrer_key.as_ref()], program_id);
+    let matches = stats.as_ref().map_or(false, |stats| stats.key() == expected_pda);
+    require!(matches || !strict, StakingError::InvalidReferrerAccount);
+    Ok(matches)
+}
+
+/// Stores the new pause flag set and emits it, decoded, for indexers.
+fn apply_pause_flags(pool: &mut Account<'_, StakingPool>, pool_entry: &mut Account<'_, PoolRegistryEntry>, new_flags: u8) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let old_flags = pool.pause_flags;
+    pool.pause_flags = new_flags;
+    pool_entry.status = pool.status(now);
+    emit!(PauseFlagsUpdated {
+        pool: pool.key(),
+        old_flags,
+        new_flags,
+        staking_paused: pool.is_paused(PAUSE_STAKE),
+        unstaking_paused: pool.is_paused(PAUSE_UNSTAKE),
+        claims_paused: pool.is_paused(PAUSE_CLAIM),
+        referral_claims_paused: pool.is_paused(PAUSE_REFERRAL_CLAIM),
+        referral_linking_paused: pool.is_paused(PAUSE_REFERRAL_LINK),
+        timestamp: now,
+    });
+    Ok(())
+}
+
+/// Closes a grant that has nothing left to release and takes it off the referrer's open grants.
+fn close_finished_grant<'info>(
+    grant: &mut Account<'info, CommissionGrant>,
+    stats: &mut Account<'info, ReferrerStats>,
+    referrer: AccountInfo<'info>,
+) -> Result<()> {
+    stats.open_grants = stats.open_grants.checked_sub(1).ok_or(StakingError::MathOverflow)?;
+    grant.close(referrer)
+}
+
+/// Removes `amount` from the active stake a referrer sees at `level` (1-3), if the supplied stats account is theirs in `pool`.
+/// Never strict: a missing upline account must not keep a user from withdrawing (unstake or emergency exit),
+/// so it's skipped and that referrer's active stake stays overstated.
+fn remove_active_stake(
+    stats: &mut Option<Account<'_, ReferrerStats>>,
+    pool: &Pubkey,
+    referrer: Option<Pubkey>,
+    level: u8,
+    amount: u64,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if !referrer_stats_matches(stats, pool, referrer, false, program_id)? {
+        return Ok(());
+    }
+    if let Some(stats) = stats {
+        let active = match level {
+            1 => &mut stats.active_stake_l1,
+            2 => &mut stats.active_stake_l2,
+            _ => &mut stats.active_stake_l3,
+        };
+        *active = active.checked_sub(amount).unwrap_or(0);
+    }
+    Ok(())
+}
+
+/// `pool` is the migrated pool itself, or the pool a stake or stats account belongs to (unset on pre-pool stats).
+fn emit_migrated(account: &AccountInfo<'_>, pool: Pubkey, old_version: u8, new_version: u8) -> Result<()> {
+    emit!(AccountMigrated {
+        account: account.key(),
+        pool,
+        old_version,
+        new_version,
+        data_len: account.data_len() as u64,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Credits the stake's L1-L3 upline with commission on `reward`, books it as pool liability and emits
+/// `CommissionCredited` with the per-level split.
+fn credit_upline_commission(
+    pool: &mut Account<'_, StakingPool>,
+    user_stake: &UserStake,
+    reward: u64,
+    stats_l1: &mut Option<Account<'_, ReferrerStats>>,
+    stats_l2: &mut Option<Account<'_, ReferrerStats>>,
+    stats_l3: &mut Option<Account<'_, ReferrerStats>>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let (pool_key, strict) = (pool.key(), pool.strict_referrer_accounts);
+    let [l1_comm, l2_comm, l3_comm] =
+        reward_math::commission_split(reward, [pool.referral_l1_bps, pool.referral_l2_bps, pool.referral_l3_bps]);
+    let l1_amount = credit_commission(stats_l1, &pool_key, user_stake.referrer, l1_comm, strict, program_id)?;
+    let l2_amount = credit_commission(stats_l2, &pool_key, user_stake.referrer_l2, l2_comm, strict, program_id)?;
+    let l3_amount = credit_commission(stats_l3, &pool_key, user_stake.referrer_l3, l3_comm, strict, program_id)?;
+    pool.reward_liability = pool.reward_liability
+        .checked_add(l1_amount).and_then(|v| v.checked_add(l2_amount)).and_then(|v| v.checked_add(l3_amount))
+        .ok_or(StakingError::MathOverflow)?;
+
+    if user_stake.referrer.is_some() {
+        emit!(CommissionCredited {
+            pool: pool.key(),
+            referee: user_stake.user,
+            reward,
+            l1_referrer: user_stake.referrer,
+            l1_amount,
+            l2_referrer: user_stake.referrer_l2,
+            l2_amount,
+            l3_referrer: user_stake.referrer_l3,
+            l3_amount,
+            reward_liability: pool.reward_liability,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// Emits `AccountFrozen` / `AccountUnfrozen` for a UserStake or ReferrerStats.
+fn emit_freeze(account: Pubkey, owner: Pubkey, pool: Pubkey, frozen: bool, reason: u8) -> Result<()> {
+    let timestamp = Clock::get()?.unix_timestamp;
+    if frozen {
+        emit!(AccountFrozen { account, owner, pool, reason, timestamp });
+    } else {
+        emit!(AccountUnfrozen { account, owner, pool, reason, timestamp });
+    }
+    Ok(())
+}
+
+/// Credits `comm` to a referrer's pending commission, if the supplied stats account is theirs in `pool`.
+/// Returns the amount credited so the caller can book it as pool liability.
+fn credit_commission(
+    stats: &mut Option<Account<'_, ReferrerStats>>,
+    pool: &Pubkey,
+    referrer: Option<Pubkey>,
+    comm: u64,
+    strict: bool,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    if !referrer_stats_matches(stats, pool, referrer, strict, program_id)? {
+        return Ok(0);
+    }
+    let Some(stats) = stats else {
+        return Ok(0);
+    };
+    if comm > 0 {
+        stats.total_commission_earned = stats.total_commission_earned.checked_add(comm).ok_or(StakingError::MathOverflow)?;
+        stats.pending_rewards = stats.pending_rewards.checked_add(comm).ok_or(StakingError::MathOverflow)?;
+    }
+    Ok(comm)
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct StakingPool {
+    pub pool_id: u64,
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub fee_receiver: Pubkey,
+    pub reward_per_token_stored: u128,
+    pub reward_rate: u64,
+    pub total_staked: u64,
+    pub fee_stake: u64,
+    pub fee_unstake: u64,
+    pub fee_claim: u64,
+    pub min_stake_amount: u64,
+    pub max_stake_per_wallet: u64, // 0 = unlimited
+    pub max_total_staked: u64,     // 0 = unlimited
+    pub allowlist_root: [u8; 32],  // All zero = no allowlist
+    pub last_update_time: i64,
+    pub lock_duration: i64,
+    pub min_referee_stake: u64,
+    pub min_referrer_stake_age: i64,
+    pub commission_vesting_cliff: i64,
+    pub commission_vesting_duration: i64,
+    pub restake_pool: Pubkey, // Pool that rewards may be restaked into (its token_mint == our reward_mint)
+    pub start_time: i64,
+    pub end_time: i64,        // 0 = open-ended; no accrual after this
+    pub reward_liability: u64, // Settled-but-unpaid rewards and commission owed from the reward vault
+    pub sunset: bool,
+    pub emergency_mode: bool, // Enables emergency_withdraw
+    pub referral_l1_bps: u16,
     pub referral_l2_bps: u16,
     pub referral_l3_bps: u16,
     pub referral_enabled: bool,
-    pub paused: bool,
+    pub strict_referrer_accounts: bool, // Stakes and claims require every upline's ReferrerStats (and L2's stake when linking) instead of skipping missing ones
+    pub pause_flags: u8, // PAUSE_* bits
     pub bump: u8,
+    // Layout version; new fields go after it and are carved out of `reserved` so the size stays fixed
+    pub version: u8,
+    pub legacy_seeds: bool, // Migrated from StakingPoolV0; still lives (and signs) at ["staking_pool", token_mint]
+    pub active_competition: Pubkey, // Unsettled competition round stakes must pass in (default = none)
+    pub reserved: [u8; 31],
 }
 
 impl StakingPool {
-    pub const SIZE: usize = 288; 
+    pub const SIZE: usize = Self::INIT_SPACE;
+    pub const VERSION: u8 = 1;
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    /// Native SOL pools wrap/unwrap lamports so users don't need a wSOL account.
+    pub fn is_native(&self) -> bool {
+        self.token_mint == native_mint::ID
+    }
+
+    /// Stakes (including restakes) are only accepted inside the schedule and never once sunset.
+    pub fn check_accepting_stakes(&self, now: i64) -> Result<()> {
+        require!(!self.sunset, StakingError::PoolSunset);
+        require!(now >= self.start_time, StakingError::PoolNotStarted);
+        require!(self.end_time == 0 || now < self.end_time, StakingError::PoolEnded);
+        Ok(())
+    }
+
+    /// Registry status for the pool's state at `now`. `Closed` is only ever set by `close_pool`, and
+    /// `Ended` is as of the last write to the entry: clients compare `end_time` themselves.
+    pub fn status(&self, now: i64) -> PoolStatus {
+        if self.sunset {
+            PoolStatus::Sunset
+        } else if self.end_time > 0 && now >= self.end_time {
+            PoolStatus::Ended
+        } else if self.pause_flags != 0 {
+            PoolStatus::Paused
+        } else {
+            PoolStatus::Active
+        }
+    }
+
+    pub fn signer_seeds(&self) -> PoolSignerSeeds {
+        PoolSignerSeeds {
+            token_mint: self.token_mint,
+            pool_id: self.pool_id.to_le_bytes(),
+            bump: [self.bump],
+            legacy_seeds: self.legacy_seeds,
+        }
+    }
+}
+
+/// Owned copy of the seeds a pool signs its vault transfers with.
+pub struct PoolSignerSeeds {
+    token_mint: Pubkey,
+    pool_id: [u8; 8],
+    bump: [u8; 1],
+    legacy_seeds: bool,
+}
+
+impl PoolSignerSeeds {
+    /// `["staking_pool", token_mint, pool_id, bump]`, without `pool_id` for pools from before the registry.
+    pub fn as_seeds(&self) -> Vec<&[u8]> {
+        let mut seeds: Vec<&[u8]> = vec![b"staking_pool", self.token_mint.as_ref()];
+        if !self.legacy_seeds {
+            seeds.push(&self.pool_id);
+        }
+        seeds.push(&self.bump);
+        seeds
+    }
+}
+
+/// `StakingPool` as created before pool ids, the registry and layout versions, at `["staking_pool", token_mint]`.
+/// Only read by `migrate_staking_pool`, which maps it field by field onto the current layout.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StakingPoolV0 {
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub fee_receiver: Pubkey,
+    pub reward_per_token_stored: u128,
+    pub reward_rate: u64,
+    pub total_staked: u64,
+    pub fee_stake: u64,
+    pub fee_unstake: u64,
+    pub fee_claim: u64,
+    pub min_stake_amount: u64,
+    pub last_update_time: i64,
+    pub lock_duration: i64,
+    pub referral_l1_bps: u16,
+    pub referral_l2_bps: u16,
+    pub referral_l3_bps: u16,
+    pub referral_enabled: bool,
+    pub paused: bool,
+    pub bump: u8,
+}
+
+impl StakingPoolV0 {
+    // Allocated size, including the slack the original layout reserved
+    pub const SIZE: usize = 288;
+
+    pub fn into_current(self, pool_id: u64) -> StakingPool {
+        StakingPool {
+            pool_id,
+            authority: self.authority,
+            token_mint: self.token_mint,
+            reward_mint: self.reward_mint,
+            staking_vault: self.staking_vault,
+            reward_vault: self.reward_vault,
+            fee_receiver: self.fee_receiver,
+            reward_per_token_stored: self.reward_per_token_stored,
+            reward_rate: self.reward_rate,
+            total_staked: self.total_staked,
+            fee_stake: self.fee_stake,
+            fee_unstake: self.fee_unstake,
+            fee_claim: self.fee_claim,
+            min_stake_amount: self.min_stake_amount,
+            max_stake_per_wallet: 0,
+            max_total_staked: 0,
+            allowlist_root: [0u8; 32],
+            last_update_time: self.last_update_time,
+            lock_duration: self.lock_duration,
+            min_referee_stake: 0,
+            min_referrer_stake_age: 0,
+            commission_vesting_cliff: 0,
+            commission_vesting_duration: 0,
+            restake_pool: Pubkey::default(),
+            start_time: 0,
+            end_time: 0,
+            // Pending rewards of V0 stakes and referrer stats are booked as each one is migrated
+            reward_liability: 0,
+            sunset: false,
+            emergency_mode: false,
+            referral_l1_bps: self.referral_l1_bps,
+            referral_l2_bps: self.referral_l2_bps,
+            referral_l3_bps: self.referral_l3_bps,
+            referral_enabled: self.referral_enabled,
+            strict_referrer_accounts: false,
+            pause_flags: if self.paused { PAUSE_ALL } else { 0 },
+            bump: self.bump,
+            version: StakingPool::VERSION,
+            legacy_seeds: true,
+            active_competition: Pubkey::default(),
+            reserved: [0u8; 31],
+        }
+    }
+}
+
+/// `ReferrerStats` as created before per-pool commission, at `["referrer_stats", referrer]`. Only read by
+/// `migrate_referrer_stats`, which adds it onto the referrer's stats in a legacy pool.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ReferrerStatsV0 {
+    pub referrer: Pubkey,
+    pub total_referrals: u64,
+    pub total_commission_earned: u64,
+    pub pending_rewards: u64,
+    pub volume_referred: u64,
+    pub active_stake_l1: u64,
+    pub active_stake_l2: u64,
+    pub active_stake_l3: u64,
+}
+
+impl ReferrerStatsV0 {
+    // Allocated size, including the slack the original layout reserved
+    pub const SIZE: usize = 96;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistry {
+    pub authority: Pubkey,
+    pub pool_count: u64, // Next pool_id; pools are 0..pool_count
+    pub bump: u8,
+    pub version: u8, // See StakingPool::version
+    pub reserved: [u8; 32],
+}
+
+impl PoolRegistry {
+    pub const VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PoolStatus {
+    Active,
+    Paused,
+    Ended,
+    Sunset,
+    Closed,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistryEntry {
+    pub pool: Pubkey,
+    pub pool_id: u64,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    #[max_len(32)] // MAX_NAME_LEN
+    pub name: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub status: PoolStatus,
+    pub bump: u8,
+    pub version: u8, // See StakingPool::version
+    pub reserved: [u8; 32],
+}
+
+impl PoolRegistryEntry {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const VERSION: u8 = 1;
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct UserStake {
     pub user: Pubkey,
     pub pool: Pubkey,
@@ -561,6 +2385,13 @@ pub struct UserStake {
     pub referrer_l2: Option<Pubkey>,
     pub referrer_l3: Option<Pubkey>,
     pub total_earned: u64,
+    pub first_stake_time: i64,
+    pub allowlist_root: [u8; 32], // Root this stake was last verified against
+    pub allowlist_cap: u64,       // Per-wallet cap from that proof (0 = none)
+    pub frozen: bool,             // Set by authority; blocks claims and use as a referrer
+    pub unstake_frozen: bool,     // Set by authority; also blocks unstake
+    pub version: u8,              // See StakingPool::version
+    pub reserved: [u8; 32],
 }
 
 impl Default for UserStake {
@@ -576,15 +2407,24 @@ impl Default for UserStake {
             referrer_l2: None,
             referrer_l3: None,
             total_earned: 0,
+            first_stake_time: 0,
+            allowlist_root: [0u8; 32],
+            allowlist_cap: 0,
+            frozen: false,
+            unstake_frozen: false,
+            version: UserStake::VERSION,
+            reserved: [0u8; 32],
         }
     }
 }
 
 impl UserStake {
-    pub const SIZE: usize = 220;
+    pub const SIZE: usize = Self::INIT_SPACE;
+    pub const VERSION: u8 = 1;
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct ReferrerStats {
     pub referrer: Pubkey,
     pub total_referrals: u64,
@@ -594,42 +2434,224 @@ pub struct ReferrerStats {
     pub active_stake_l1: u64, // NEW
     pub active_stake_l2: u64, // NEW
     pub active_stake_l3: u64, // NEW
+    pub grant_count: u64,        // Commission vesting grants created since these stats were opened
+    pub flagged: bool,           // Set by authority; blocks new grants and allows clawback
+    pub frozen: bool,            // Set by authority; blocks claims and new referral links
+    pub version: u8,             // See StakingPool::version
+    pub pool: Pubkey,            // Pool whose commission this tracks (VERSION 2; default on older accounts)
+    pub open_grants: u64,        // Grants not yet paid out in full; each grant account is closed once it is
+    pub reserved: [u8; 24],
 }
 
 impl ReferrerStats {
-    // 8 + 32 + 8 + 8 + 8 + 8 + 24 (3*u64) = 96
-    pub const SIZE: usize = 96; 
+    pub const SIZE: usize = Self::INIT_SPACE;
+    pub const VERSION: u8 = 2;
 }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = authority, space = 8 + StakingPool::SIZE, seeds = [b"staking_pool", token_mint.key().as_ref()], bump)]
-    pub staking_pool: Account<'info, StakingPool>,
-    pub token_mint: Account<'info, Mint>,
-    pub reward_mint: Account<'info, Mint>,
-    #[account(init_if_needed, payer = authority, associated_token::mint = token_mint, associated_token::authority = staking_pool)]
-    pub staking_vault: Account<'info, TokenAccount>,
-    #[account(init_if_needed, payer = authority, associated_token::mint = reward_mint, associated_token::authority = staking_pool)]
-    pub reward_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
+#[account]
+#[derive(InitSpace)]
+pub struct CommissionGrant {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub index: u64,
+    pub total_amount: u64,
+    pub released_amount: u64,
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub bump: u8,
+    pub version: u8, // See StakingPool::version
+    pub reserved: [u8; 32],
+}
+
+impl CommissionGrant {
+    pub const VERSION: u8 = 1;
+
+    /// Linear vesting from `start_time` to `end_time`, nothing before `cliff_time`.
+    pub fn vested_amount(&self, now: i64) -> u64 {
+        if now < self.cliff_time {
+            return 0;
+        }
+        if now >= self.end_time || self.end_time <= self.start_time {
+            return self.total_amount;
+        }
+        let elapsed = (now - self.start_time) as u128;
+        let duration = (self.end_time - self.start_time) as u128;
+        (self.total_amount as u128 * elapsed / duration) as u64
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AllowlistProof {
+    pub cap: u64, // Per-wallet cap encoded in the leaf (0 = none)
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimDestination {
+    Wallet,
+    RestakeThisPool,
+    RestakeOtherPool, // Into `restake_pool`; needs an existing UserStake of the user there
+}
+
+/// Return data of `get_pool_state`; accumulator figures are brought up to `timestamp`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolState {
+    pub total_staked: u64,
+    pub reward_rate: u64,
+    pub reward_per_token_stored: u128,
+    pub reward_vault_balance: u64,
+    pub reward_liability: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub lock_duration: i64,
+    pub pause_flags: u8,
+    pub sunset: bool,
+    pub emergency_mode: bool,
+    pub accepting_stakes: bool,
+    pub timestamp: i64,
+}
+
+/// Return data of `get_referrer_summary`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ReferrerSummary {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub total_referrals: u64,
+    pub pending_rewards: u64,
+    pub total_commission_earned: u64,
+    pub volume_referred: u64,
+    pub active_stake_l1: u64,
+    pub active_stake_l2: u64,
+    pub active_stake_l3: u64,
+    pub grant_count: u64,
+    pub flagged: bool,
+    pub frozen: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct LeaderboardEntry {
+    pub referrer: Pubkey,
+    pub volume: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralCompetition {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub prize_vault: Pubkey,
+    #[max_len(10)] // MAX_WINNERS
+    pub prize_split_bps: Vec<u16>,
+    #[max_len(10)] // MAX_WINNERS; sorted by volume, descending
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub total_volume: u64,
+    pub settled: bool,
+    pub bump: u8,
+    pub version: u8, // See StakingPool::version
+    pub reserved: [u8; 32],
+}
+
+/// A referrer's referred volume in one competition round.
+#[account]
+#[derive(InitSpace)]
+pub struct CompetitionEntry {
+    pub competition: Pubkey,
+    pub referrer: Pubkey,
+    pub volume: u64,
+    pub bump: u8,
+    pub version: u8, // See StakingPool::version
+    pub reserved: [u8; 32],
+}
+
+impl CompetitionEntry {
+    pub const VERSION: u8 = 1;
+}
+
+impl ReferralCompetition {
+    pub const MAX_WINNERS: usize = 10;
+    pub const VERSION: u8 = 1;
+
+    pub fn is_live(&self, now: i64) -> bool {
+        !self.settled && now >= self.start_time && now < self.end_time
+    }
+
+    /// Upserts `referrer` with its running round volume, keeping only the top `prize_split_bps.len()` entries.
+    pub fn record_volume(&mut self, referrer: Pubkey, volume: u64) {
+        let max_entries = self.prize_split_bps.len();
+        if let Some(entry) = self.leaderboard.iter_mut().find(|e| e.referrer == referrer) {
+            entry.volume = volume;
+        } else if self.leaderboard.len() < max_entries {
+            self.leaderboard.push(LeaderboardEntry { referrer, volume });
+        } else if let Some(last) = self.leaderboard.last_mut() {
+            if volume <= last.volume {
+                return;
+            }
+            *last = LeaderboardEntry { referrer, volume };
+        }
+        self.leaderboard.sort_by(|a, b| b.volume.cmp(&a.volume));
+    }
+}
 
 #[derive(Accounts)]
-pub struct StakeWithReferral<'info> {
+pub struct InitializeRegistry<'info> {
+    #[account(init, payer = authority, space = 8 + PoolRegistry::INIT_SPACE, seeds = [b"pool_registry"], bump)]
+    pub registry: Account<'info, PoolRegistry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    // Only the upgrade authority may claim the registry, so nobody can front-run the deploy
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ StakingError::Unauthorized)]
+    pub program: Program<'info, crate::program::SolanaStakingReferral>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ StakingError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut, has_one = authority @ StakingError::Unauthorized, seeds = [b"pool_registry"], bump = registry.bump)]
+    pub registry: Account<'info, PoolRegistry>,
+    #[account(init, payer = authority, space = 8 + StakingPool::SIZE, seeds = [b"staking_pool", token_mint.key().as_ref(), &registry.pool_count.to_le_bytes()], bump)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(init, payer = authority, space = 8 + PoolRegistryEntry::INIT_SPACE, seeds = [b"pool_entry", &registry.pool_count.to_le_bytes()], bump)]
+    pub pool_entry: Account<'info, PoolRegistryEntry>,
+    #[account(mint::token_program = staking_token_program)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    // Vaults are PDAs of the pool, so each pool gets its own even when token_mint == reward_mint
+    #[account(init, payer = authority, seeds = [b"staking_vault", staking_pool.key().as_ref()], bump, token::mint = token_mint, token::authority = staking_pool, token::token_program = staking_token_program)]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(init, payer = authority, seeds = [b"reward_vault", staking_pool.key().as_ref()], bump, token::mint = reward_mint, token::authority = staking_pool, token::token_program = reward_token_program)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // The mints may live under different token programs (e.g. SPL Token stake, Token-2022 rewards)
+    pub staking_token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct StakeWithReferral<'info> {
+    #[account(mut, has_one = staking_vault @ StakingError::InvalidStakingVault)]
     pub staking_pool: Account<'info, StakingPool>,
     #[account(init_if_needed, payer = user, space = 8 + UserStake::SIZE, seeds = [b"user_stake", user.key().as_ref(), staking_pool.key().as_ref()], bump)]
     pub user_stake: Box<Account<'info, UserStake>>,
     #[account(mut)]
-    pub staking_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver)]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == staking_pool.token_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = user_token_account.owner == user.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // None = wrap SOL (native pools only)
+    #[account(address = staking_pool.token_mint @ StakingError::InvalidMint)]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver @ StakingError::InvalidFeeReceiver)]
     pub fee_receiver_account: AccountInfo<'info>,
     #[account(mut)]
     pub referrer_stats: Option<Account<'info, ReferrerStats>>,
@@ -643,23 +2665,40 @@ pub struct StakeWithReferral<'info> {
     pub l2_user_stake: Option<AccountInfo<'info>>,
     #[account(mut)]
     pub referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
+    // The pool's `active_competition`; required while one is set
+    #[account(mut, constraint = competition.pool == staking_pool.key() @ StakingError::InvalidCompetition)]
+    pub competition: Option<Box<Account<'info, ReferralCompetition>>>,
+    /// CHECK: L1's entry PDA in `competition`, verified in the instruction body; required while the round is live
+    /// and only credited once the referrer has entered
+    #[account(mut)]
+    pub competition_entry: Option<UncheckedAccount<'info>>,
     #[account(mut)]
     pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct ClaimRewards<'info> {
-    #[account(mut)]
+    #[account(mut, has_one = reward_vault @ StakingError::InvalidRewardVault)]
     pub staking_pool: Account<'info, StakingPool>,
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = user @ StakingError::InvalidUserStakeOwner,
+        constraint = user_stake.pool == staking_pool.key() @ StakingError::InvalidUserStakePool,
+    )]
     pub user_stake: Box<Account<'info, UserStake>>,
     #[account(mut)]
-    pub reward_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_reward_account: Account<'info, TokenAccount>,
-    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_reward_account.mint == staking_pool.reward_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = user_reward_account.owner == user.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub user_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver @ StakingError::InvalidFeeReceiver)]
     pub fee_receiver_account: AccountInfo<'info>,
     #[account(mut)]
     pub referrer_stats_l1: Option<Account<'info, ReferrerStats>>,
@@ -667,38 +2706,221 @@ pub struct ClaimRewards<'info> {
     pub referrer_stats_l2: Option<Account<'info, ReferrerStats>>,
     #[account(mut)]
     pub referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
+    // --- Restake destinations (only needed for the matching ClaimDestination) ---
+    #[account(mut, constraint = staking_vault.key() == staking_pool.staking_vault @ StakingError::InvalidStakingVault)]
+    pub staking_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = target_pool.key() != staking_pool.key() @ StakingError::InvalidRestakeTarget)]
+    pub target_pool: Option<Box<Account<'info, StakingPool>>>,
+    // Not created here: the user must already hold a stake in the target pool, made through
+    // stake_with_referral, so it carries their referral link there
+    #[account(mut, constraint = target_user_stake.key() != user_stake.key() @ StakingError::InvalidRestakeTarget)]
+    pub target_user_stake: Option<Box<Account<'info, UserStake>>>,
+    #[account(mut)]
+    pub target_staking_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    // The target stake's upline in the target pool, whose active stake grows by the restake
+    #[account(mut)]
+    pub target_referrer_stats_l1: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub target_referrer_stats_l2: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub target_referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
     #[account(mut)]
     pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimReferralRewards<'info> {
+pub struct ClaimAll<'info> {
     #[account(mut)]
     pub staking_pool: Account<'info, StakingPool>,
-    #[account(mut, seeds = [b"referrer_stats", referrer.key().as_ref()], bump, constraint = referrer_stats.referrer == referrer.key())]
+    #[account(mut, seeds = [b"user_stake", user.key().as_ref(), staking_pool.key().as_ref()], bump, has_one = user @ StakingError::InvalidUserStakeOwner)]
+    pub user_stake: Box<Account<'info, UserStake>>,
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = user_referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
+    pub user_referrer_stats: Option<Account<'info, ReferrerStats>>,
+    #[account(mut, constraint = reward_vault.key() == staking_pool.reward_vault @ StakingError::InvalidRewardVault)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_reward_account.mint == staking_pool.reward_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = user_reward_account.owner == user.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub user_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub referrer_stats_l1: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub referrer_stats_l2: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut, has_one = reward_vault @ StakingError::InvalidRewardVault)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        constraint = referrer_stats.referrer == referrer.key() @ StakingError::InvalidReferrerAccount,
+        constraint = referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
     pub referrer_stats: Account<'info, ReferrerStats>,
     #[account(mut)]
-    pub reward_vault: Account<'info, TokenAccount>,
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = referrer_reward_account.mint == staking_pool.reward_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = referrer_reward_account.owner == referrer.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub referrer_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub referrer_reward_account: Account<'info, TokenAccount>,
+    pub referrer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct VestReferralRewards<'info> {
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        constraint = referrer_stats.referrer == referrer.key() @ StakingError::InvalidReferrerAccount,
+        constraint = referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    #[account(
+        init,
+        payer = referrer,
+        space = 8 + CommissionGrant::INIT_SPACE,
+        seeds = [b"commission_grant", staking_pool.key().as_ref(), referrer.key().as_ref(), &referrer_stats.grant_count.to_le_bytes()],
+        bump,
+    )]
+    pub grant: Account<'info, CommissionGrant>,
     #[account(mut)]
     pub referrer: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Unstake<'info> {
+pub struct ReleaseVestedCommission<'info> {
     #[account(mut)]
     pub staking_pool: Account<'info, StakingPool>,
+    #[account(
+        mut,
+        seeds = [b"commission_grant", grant.pool.as_ref(), grant.referrer.as_ref(), &grant.index.to_le_bytes()],
+        bump = grant.bump,
+        has_one = referrer @ StakingError::InvalidCommissionGrant,
+        constraint = grant.pool == staking_pool.key() @ StakingError::InvalidCommissionGrant,
+    )]
+    pub grant: Account<'info, CommissionGrant>,
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        constraint = referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    #[account(mut, constraint = reward_vault.key() == staking_pool.reward_vault @ StakingError::InvalidRewardVault)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = referrer_reward_account.mint == staking_pool.reward_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = referrer_reward_account.owner == referrer.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub referrer_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
+    pub referrer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FlagReferrer<'info> {
+    #[account(has_one = authority @ StakingError::Unauthorized)]
+    pub staking_pool: Account<'info, StakingPool>,
+    // Only the authority of the pool the commission was earned in may flag or freeze it
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer_stats.referrer.as_ref()],
+        bump,
+        constraint = referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeUserStake<'info> {
+    #[account(has_one = authority @ StakingError::Unauthorized)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut, constraint = user_stake.pool == staking_pool.key() @ StakingError::InvalidUserStakePool)]
+    pub user_stake: Account<'info, UserStake>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClawbackCommissionGrant<'info> {
+    #[account(mut, has_one = authority @ StakingError::Unauthorized)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), grant.referrer.as_ref()],
+        bump,
+        constraint = referrer_stats.pool == staking_pool.key() @ StakingError::InvalidReferrerAccount,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    #[account(
+        mut,
+        seeds = [b"commission_grant", grant.pool.as_ref(), grant.referrer.as_ref(), &grant.index.to_le_bytes()],
+        bump = grant.bump,
+        constraint = grant.pool == staking_pool.key() @ StakingError::InvalidCommissionGrant,
+    )]
+    pub grant: Account<'info, CommissionGrant>,
+    /// CHECK: Receives the grant's rent if the clawback finishes it
+    #[account(mut, address = grant.referrer @ StakingError::InvalidCommissionGrant)]
+    pub referrer: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(mut, has_one = staking_vault @ StakingError::InvalidStakingVault)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(
+        mut,
+        has_one = user @ StakingError::InvalidUserStakeOwner,
+        constraint = user_stake.pool == staking_pool.key() @ StakingError::InvalidUserStakePool,
+    )]
     pub user_stake: Account<'info, UserStake>,
     #[account(mut)]
-    pub staking_vault: Account<'info, TokenAccount>,
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == staking_pool.token_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = user_token_account.owner == user.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // None = unwrap to SOL (native pools only)
+    /// CHECK: PDA ["native_unwrap", user]; verified and created in the instruction body
     #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver)]
+    pub native_unwrap_account: Option<UncheckedAccount<'info>>,
+    #[account(address = staking_pool.token_mint @ StakingError::InvalidMint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, constraint = fee_receiver_account.key() == staking_pool.fee_receiver @ StakingError::InvalidFeeReceiver)]
     pub fee_receiver_account: AccountInfo<'info>,
     // --- Added for Live Referral Stats ---
     #[account(mut)]
@@ -709,32 +2931,206 @@ pub struct Unstake<'info> {
     pub referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
     #[account(mut)]
     pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(mut, has_one = staking_vault @ StakingError::InvalidStakingVault)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut, seeds = [b"user_stake", user.key().as_ref(), staking_pool.key().as_ref()], bump, has_one = user @ StakingError::InvalidUserStakeOwner)]
+    pub user_stake: Account<'info, UserStake>,
+    #[account(mut)]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == staking_pool.token_mint @ StakingError::InvalidTokenAccountMint,
+        constraint = user_token_account.owner == user.key() @ StakingError::InvalidTokenAccountOwner,
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // None = unwrap to SOL (native pools only)
+    /// CHECK: PDA ["native_unwrap", user]; verified and created in the instruction body
+    #[account(mut)]
+    pub native_unwrap_account: Option<UncheckedAccount<'info>>,
+    #[account(address = staking_pool.token_mint @ StakingError::InvalidMint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub referrer_stats_l2: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub referrer_stats_l3: Option<Account<'info, ReferrerStats>>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserStake<'info> {
+    // Seeded by the stored pool so stakes can still be closed after close_pool
+    #[account(
+        mut,
+        seeds = [b"user_stake", user.key().as_ref(), user_stake.pool.as_ref()],
+        bump,
+        has_one = user @ StakingError::InvalidUserStakeOwner,
+        close = user,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseReferrerStats<'info> {
+    // Seeded by the stored pool so stats can still be closed after close_pool
+    #[account(
+        mut,
+        seeds = [b"referrer_stats", referrer_stats.pool.as_ref(), referrer.key().as_ref()],
+        bump,
+        constraint = referrer_stats.referrer == referrer.key() @ StakingError::InvalidReferrerAccount,
+        close = referrer,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ViewUserStake<'info> {
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(constraint = user_stake.pool == staking_pool.key() @ StakingError::InvalidUserStakePool)]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct ViewPool<'info> {
+    #[account(has_one = reward_vault @ StakingError::InvalidRewardVault)]
+    pub staking_pool: Account<'info, StakingPool>,
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ViewReferrer<'info> {
+    #[account(seeds = [b"referrer_stats", referrer_stats.pool.as_ref(), referrer_stats.referrer.as_ref()], bump)]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateStakingPool<'info> {
+    /// CHECK: Still in the StakingPoolV0 layout; owner, discriminator, size and address are checked in the instruction body
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"pool_registry"], bump = registry.bump)]
+    pub registry: Account<'info, PoolRegistry>,
+    #[account(init, payer = payer, space = 8 + PoolRegistryEntry::INIT_SPACE, seeds = [b"pool_entry", &registry.pool_count.to_le_bytes()], bump)]
+    pub pool_entry: Account<'info, PoolRegistryEntry>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserStake<'info> {
+    /// CHECK: May still be in a pre-versioning layout that doesn't deserialize; owner and discriminator are checked in the instruction body
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateReferrerStats<'info> {
+    /// CHECK: Pre-per-pool stats at ["referrer_stats", referrer]; address, owner and discriminator are checked in the instruction body
+    #[account(mut)]
+    pub legacy_stats: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        has_one = authority @ StakingError::Unauthorized,
+        constraint = staking_pool.legacy_seeds @ StakingError::InvalidMigrationAccount,
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+    // May already exist if the referrer was credited in this pool after it was migrated
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ReferrerStats::SIZE,
+        seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer.key().as_ref()],
+        bump,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+    /// CHECK: Checked against the legacy stats in the instruction body; only receives their rent
+    #[account(mut)]
+    pub referrer: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct AdminUpdate<'info> {
-    #[account(mut, has_one = authority)]
+    #[account(mut, has_one = authority @ StakingError::Unauthorized)]
     pub staking_pool: Account<'info, StakingPool>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawTokens<'info> {
+pub struct UpdatePoolMetadata<'info> {
+    #[account(mut, has_one = authority @ StakingError::Unauthorized)]
     pub staking_pool: Account<'info, StakingPool>,
-    #[account(mut, constraint = staking_vault.key() == staking_pool.staking_vault)]
-    pub staking_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"pool_entry", &staking_pool.pool_id.to_le_bytes()], bump = pool_entry.bump)]
+    pub pool_entry: Account<'info, PoolRegistryEntry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut, has_one = authority @ StakingError::Unauthorized, has_one = staking_vault @ StakingError::InvalidStakingVault, has_one = reward_vault @ StakingError::InvalidRewardVault, close = authority)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut, seeds = [b"pool_entry", &staking_pool.pool_id.to_le_bytes()], bump = pool_entry.bump)]
+    pub pool_entry: Account<'info, PoolRegistryEntry>,
+    #[account(mut)]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub admin_token_account: Account<'info, TokenAccount>,
-    #[account(address = staking_pool.authority)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.token_mint @ StakingError::InvalidMint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = token_mint)]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = reward_mint)]
+    pub authority_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(address = *token_mint.to_account_info().owner @ StakingError::InvalidMint)]
+    pub staking_token_program: Interface<'info, TokenInterface>,
+    #[account(address = *reward_mint.to_account_info().owner @ StakingError::InvalidMint)]
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut, constraint = staking_vault.key() == staking_pool.staking_vault @ StakingError::InvalidStakingVault)]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = admin_token_account.mint == staking_pool.token_mint @ StakingError::InvalidTokenAccountMint)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.token_mint @ StakingError::InvalidMint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = staking_pool.authority @ StakingError::Unauthorized)]
     pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
 pub struct InitializeReferrerStats<'info> {
-    #[account(init, payer = referrer, space = 8 + ReferrerStats::SIZE, seeds = [b"referrer_stats", referrer.key().as_ref()], bump)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(init, payer = referrer, space = 8 + ReferrerStats::SIZE, seeds = [b"referrer_stats", staking_pool.key().as_ref(), referrer.key().as_ref()], bump)]
     pub referrer_stats: Account<'info, ReferrerStats>,
     #[account(mut)]
     pub referrer: Signer<'info>,
@@ -743,40 +3139,546 @@ pub struct InitializeReferrerStats<'info> {
 
 #[derive(Accounts)]
 pub struct AddRewards<'info> {
+    #[account(has_one = reward_vault @ StakingError::InvalidRewardVault)]
     pub staking_pool: Account<'info, StakingPool>,
     #[account(mut)]
-    pub reward_vault: Account<'info, TokenAccount>,
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = funder_reward_account.mint == staking_pool.reward_mint @ StakingError::InvalidTokenAccountMint)]
+    pub funder_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = staking_pool.reward_mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub funder_reward_account: Account<'info, TokenAccount>,
+    pub funder: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct CreateCompetition<'info> {
+    #[account(mut, has_one = authority @ StakingError::Unauthorized, has_one = reward_mint @ StakingError::InvalidMint)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(init, payer = authority, space = 8 + ReferralCompetition::INIT_SPACE, seeds = [b"referral_competition", staking_pool.key().as_ref(), &round_id.to_le_bytes()], bump)]
+    pub competition: Account<'info, ReferralCompetition>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(init_if_needed, payer = authority, associated_token::mint = reward_mint, associated_token::authority = competition, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundCompetition<'info> {
+    pub competition: Account<'info, ReferralCompetition>,
+    #[account(mut, constraint = prize_vault.key() == competition.prize_vault @ StakingError::InvalidPrizeVault)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = funder_reward_account.mint == prize_vault.mint @ StakingError::InvalidTokenAccountMint)]
+    pub funder_reward_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = reward_mint.key() == prize_vault.mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
     pub funder: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct EnterCompetition<'info> {
+    pub competition: Account<'info, ReferralCompetition>,
+    #[account(init, payer = referrer, space = 8 + CompetitionEntry::INIT_SPACE, seeds = [b"competition_entry", competition.key().as_ref(), referrer.key().as_ref()], bump)]
+    pub competition_entry: Account<'info, CompetitionEntry>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleCompetition<'info> {
+    #[account(mut)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(mut, constraint = competition.pool == staking_pool.key() @ StakingError::InvalidCompetition)]
+    pub competition: Account<'info, ReferralCompetition>,
+    #[account(mut, constraint = prize_vault.key() == competition.prize_vault @ StakingError::InvalidPrizeVault)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = reward_mint.key() == prize_vault.mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SweepCompetitionRemainder<'info> {
+    #[account(has_one = authority @ StakingError::Unauthorized)]
+    pub staking_pool: Account<'info, StakingPool>,
+    #[account(constraint = competition.pool == staking_pool.key() @ StakingError::InvalidCompetition)]
+    pub competition: Account<'info, ReferralCompetition>,
+    #[account(mut, constraint = prize_vault.key() == competition.prize_vault @ StakingError::InvalidPrizeVault)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = destination.mint == prize_vault.mint @ StakingError::InvalidTokenAccountMint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = reward_mint.key() == prize_vault.mint @ StakingError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Every state-changing instruction emits one of these. Admin updates carry old and new values, user
+// actions the post-state totals, so pool history can be rebuilt from logs alone.
+
+#[event]
+pub struct RegistryInitialized {
+    pub registry: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolInitialized {
+    pub pool: Pubkey,
+    pub pool_id: u64,
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_rate: u64,
+    pub referral_enabled: bool,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct NewReferral {
     pub user: Pubkey,
+    pub pool: Pubkey,
     pub referrer: Pubkey,
+    pub referrer_l2: Option<Pubkey>,
+    pub referrer_l3: Option<Pubkey>,
     pub timestamp: i64,
 }
 
 #[event]
 pub struct Staked {
     pub user: Pubkey,
-    pub amount: u64,
+    pub pool: Pubkey,
+    pub amount: u64,      // Sent by the user
+    pub net_amount: u64,  // Received by the vault and credited
+    pub user_staked: u64, // User's stake afterwards
+    pub total_staked: u64,
     pub timestamp: i64,
 }
 
 #[event]
 pub struct Unstaked {
     pub user: Pubkey,
-    pub amount: u64,
+    pub pool: Pubkey,
+    pub amount: u64,      // Debited from the stake
+    pub net_amount: u64,  // Received by the user after transfer fees
+    pub user_staked: u64, // User's stake afterwards
+    pub total_staked: u64,
     pub timestamp: i64,
 }
 
 #[event]
 pub struct RewardsClaimed {
     pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub net_amount: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommissionCredited {
+    pub pool: Pubkey,
+    pub referee: Pubkey,
+    pub reward: u64, // Staking reward the commission is taken from
+    pub l1_referrer: Option<Pubkey>,
+    pub l1_amount: u64,
+    pub l2_referrer: Option<Pubkey>,
+    pub l2_amount: u64,
+    pub l3_referrer: Option<Pubkey>,
+    pub l3_amount: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub net_amount: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerStatsInitialized {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub referrer_stats: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerFlagUpdated {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub old_flagged: bool,
+    pub new_flagged: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesUpdated {
+    pub pool: Pubkey,
+    pub old_fee_stake: u64,
+    pub new_fee_stake: u64,
+    pub old_fee_unstake: u64,
+    pub new_fee_unstake: u64,
+    pub old_fee_claim: u64,
+    pub new_fee_claim: u64,
+    pub old_fee_receiver: Pubkey,
+    pub new_fee_receiver: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRatesUpdated {
+    pub pool: Pubkey,
+    pub old_l1_bps: u16,
+    pub new_l1_bps: u16,
+    pub old_l2_bps: u16,
+    pub new_l2_bps: u16,
+    pub old_l3_bps: u16,
+    pub new_l3_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockDurationUpdated {
+    pub pool: Pubkey,
+    pub old_duration: i64,
+    pub new_duration: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardRateUpdated {
+    pub pool: Pubkey,
+    pub old_rate: u64,
+    pub new_rate: u64,
+    pub reward_per_token_stored: u128, // Settled under the old rate
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyModeUpdated {
+    pub pool: Pubkey,
+    pub old_enabled: bool,
+    pub new_enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolMetadataUpdated {
+    pub pool: Pubkey,
+    pub name: String,
+    pub old_start_time: i64,
+    pub new_start_time: i64,
+    pub old_end_time: i64,
+    pub new_end_time: i64,
+    pub old_status: PoolStatus,
+    pub new_status: PoolStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolSunsetStarted {
+    pub pool: Pubkey,
+    pub end_time: i64,
+    pub total_staked: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolClosed {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub swept_stake_tokens: u64,
+    pub swept_reward_tokens: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeLimitsUpdated {
+    pub pool: Pubkey,
+    pub old_max_stake_per_wallet: u64,
+    pub new_max_stake_per_wallet: u64,
+    pub old_max_total_staked: u64,
+    pub new_max_total_staked: u64,
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AllowlistRootUpdated {
+    pub pool: Pubkey,
+    pub old_root: [u8; 32],
+    pub new_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RestakePoolUpdated {
+    pub pool: Pubkey,
+    pub old_restake_pool: Pubkey,
+    pub new_restake_pool: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommissionVestingUpdated {
+    pub pool: Pubkey,
+    pub old_cliff: i64,
+    pub new_cliff: i64,
+    pub old_duration: i64,
+    pub new_duration: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralGuardsUpdated {
+    pub pool: Pubkey,
+    pub old_min_referee_stake: u64,
+    pub new_min_referee_stake: u64,
+    pub old_min_referrer_stake_age: i64,
+    pub new_min_referrer_stake_age: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralSystemToggled {
+    pub pool: Pubkey,
+    pub old_enabled: bool,
+    pub new_enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StrictReferrerAccountsUpdated {
+    pub pool: Pubkey,
+    pub old_enabled: bool,
+    pub new_enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokensWithdrawn {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub vault_balance: u64, // Staking vault afterwards
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsAdded {
+    pub pool: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub reward_vault_balance: u64, // Afterwards
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserStakeClosed {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub user_stake: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerStatsClosed {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub referrer_stats: Pubkey,
+    pub total_referrals: u64,
+    pub total_commission_earned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub pool: Pubkey,
+    pub old_version: u8,
+    pub new_version: u8,
+    pub data_len: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub pool: Pubkey,
+    pub old_flags: u8,
+    pub new_flags: u8,
+    pub staking_paused: bool,
+    pub unstaking_paused: bool,
+    pub claims_paused: bool,
+    pub referral_claims_paused: bool,
+    pub referral_linking_paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawn {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub net_amount: u64,
+    pub forfeited_rewards: u64,
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsRestaked {
+    pub user: Pubkey,
+    pub from_pool: Pubkey,
+    pub to_pool: Pubkey,
+    pub amount: u64,
+    pub net_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AllRewardsClaimed {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub staking_reward: u64,
+    pub referral_reward: u64,
+    pub total: u64,
+    pub net_total: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommissionVested {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub grant: Pubkey,
+    pub amount: u64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub pending_rewards: u64,
+    pub grant_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommissionReleased {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub grant: Pubkey,
+    pub amount: u64,
+    pub released_amount: u64,
+    pub remaining_amount: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommissionClawedBack {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub grant: Pubkey,
+    pub amount: u64,
+    pub total_amount: u64,
+    pub released_amount: u64,
+    pub reward_liability: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountFrozen {
+    pub account: Pubkey, // UserStake or ReferrerStats
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub reason: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountUnfrozen {
+    pub account: Pubkey,
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub reason: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionCreated {
+    pub competition: Pubkey,
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub prize_split_bps: Vec<u16>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionFunded {
+    pub competition: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub prize_pool: u64, // Prize vault balance afterwards
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionSettled {
+    pub competition: Pubkey,
+    pub pool: Pubkey,
+    pub prize_pool: u64,
+    pub winners: u8,
+    pub total_volume: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionPrizePaid {
+    pub competition: Pubkey,
+    pub referrer: Pubkey,
+    pub rank: u8,
+    pub volume: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionEntered {
+    pub competition: Pubkey,
+    pub referrer: Pubkey,
+    pub competition_entry: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompetitionRemainderSwept {
+    pub competition: Pubkey,
+    pub pool: Pubkey,
+    pub destination: Pubkey,
     pub amount: u64,
     pub timestamp: i64,
 }
@@ -803,4 +3705,116 @@ pub enum StakingError {
     BelowMinimumStake,
     #[msg("Total referral rates exceed 25%")]
     ReferralRatesExceedMax,
+    #[msg("Referral chain contains a cycle")]
+    ReferralCycle,
+    #[msg("Referrer's stake is too young to refer others")]
+    ReferrerStakeTooYoung,
+    #[msg("Competition does not belong to this pool")]
+    InvalidCompetition,
+    #[msg("Competition end time must be after start time")]
+    InvalidCompetitionWindow,
+    #[msg("Invalid prize split")]
+    InvalidPrizeSplit,
+    #[msg("Competition has not ended yet")]
+    CompetitionNotEnded,
+    #[msg("Competition already settled")]
+    CompetitionAlreadySettled,
+    #[msg("Invalid winner token account")]
+    InvalidWinnerAccount,
+    #[msg("Commission vesting is enabled; use vest_referral_rewards")]
+    CommissionVestingEnabled,
+    #[msg("Commission vesting is disabled")]
+    CommissionVestingDisabled,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Referrer is flagged")]
+    ReferrerFlagged,
+    #[msg("Referrer is not flagged")]
+    ReferrerNotFlagged,
+    #[msg("Nothing to claw back")]
+    NothingToClawBack,
+    #[msg("Invalid restake destination")]
+    InvalidRestakeTarget,
+    #[msg("Mint uses an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Token account is required for this pool")]
+    MissingTokenAccount,
+    #[msg("Invalid native unwrap account")]
+    InvalidNativeUnwrapAccount,
+    #[msg("Pool name is too long")]
+    PoolNameTooLong,
+    #[msg("Pool end time must be after start time")]
+    InvalidPoolSchedule,
+    #[msg("Pool has not started yet")]
+    PoolNotStarted,
+    #[msg("Pool has ended")]
+    PoolEnded,
+    #[msg("Pool is sunset")]
+    PoolSunset,
+    #[msg("Pool must be sunset first")]
+    PoolNotSunset,
+    #[msg("Pool still has stake or unpaid rewards")]
+    PoolHasLiabilities,
+    #[msg("Emergency mode is not active")]
+    EmergencyModeNotActive,
+    #[msg("Unstaking is paused")]
+    UnstakingPaused,
+    #[msg("Reward claims are paused")]
+    ClaimsPaused,
+    #[msg("Referral claims are paused")]
+    ReferralClaimsPaused,
+    #[msg("Unknown pause flag")]
+    InvalidPauseFlags,
+    #[msg("Stake would exceed the per-wallet cap")]
+    WalletStakeCapExceeded,
+    #[msg("Stake would exceed the pool capacity")]
+    PoolCapacityExceeded,
+    #[msg("Allowlist proof required")]
+    AllowlistProofRequired,
+    #[msg("Invalid allowlist proof")]
+    InvalidAllowlistProof,
+    #[msg("Account is frozen")]
+    AccountFrozen,
+    #[msg("Referrer is frozen")]
+    ReferrerFrozen,
+    #[msg("Signer is not the authority")]
+    Unauthorized,
+    #[msg("Staking vault does not belong to this pool")]
+    InvalidStakingVault,
+    #[msg("Reward vault does not belong to this pool")]
+    InvalidRewardVault,
+    #[msg("Prize vault does not belong to this competition")]
+    InvalidPrizeVault,
+    #[msg("Mint does not match the pool")]
+    InvalidMint,
+    #[msg("User stake belongs to another wallet")]
+    InvalidUserStakeOwner,
+    #[msg("User stake belongs to another pool")]
+    InvalidUserStakePool,
+    #[msg("Token account has the wrong mint")]
+    InvalidTokenAccountMint,
+    #[msg("Token account has the wrong owner")]
+    InvalidTokenAccountOwner,
+    #[msg("Fee receiver does not match the pool")]
+    InvalidFeeReceiver,
+    #[msg("Account is not a migratable account of this type")]
+    InvalidMigrationAccount,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Stake still holds tokens or unclaimed rewards")]
+    StakeNotEmpty,
+    #[msg("Referrer stats still have pending commission, active downline stake or grants")]
+    ReferrerStatsInUse,
+    #[msg("Commission grant belongs to another pool or referrer")]
+    InvalidCommissionGrant,
+    #[msg("Account must be migrated first")]
+    AccountNotMigrated,
+    #[msg("Competition has not been settled yet")]
+    CompetitionNotSettled,
+    #[msg("Amount exceeds the vault balance above staked tokens and owed rewards")]
+    WithdrawExceedsSurplus,
+    #[msg("The pool's active competition round and the referrer's entry must be passed")]
+    CompetitionAccountsRequired,
+    #[msg("The pool already has an unsettled competition round")]
+    CompetitionAlreadyActive,
 }
\ No newline at end of file
@@ -0,0 +1,26 @@
+//! Merkle allowlist proofs.
+//!
+//! Trees use keccak256 with sorted pairs (the OpenZeppelin `MerkleProof` layout), so roots can be
+//! built with standard off-chain tooling. Leaves are 40 bytes and inner nodes 64, so a leaf can
+//! never be passed off as an inner node.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak::hashv;
+
+/// Leaf for `wallet` with a per-wallet `cap` (0 = no cap).
+pub fn leaf(wallet: &Pubkey, cap: u64) -> [u8; 32] {
+    hashv(&[wallet.as_ref(), &cap.to_le_bytes()]).0
+}
+
+/// Returns true if `proof` links `leaf` to `root`.
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed = leaf;
+    for node in proof {
+        computed = if computed <= *node {
+            hashv(&[&computed, node]).0
+        } else {
+            hashv(&[node, &computed]).0
+        };
+    }
+    computed == *root
+}
@@ -0,0 +1,130 @@
+use stake_client::{
+    instruction::PoolKeys,
+    state::{StakingPool, PAUSE_CLAIM, PAUSE_REFERRAL_CLAIM, PAUSE_REFERRAL_LINK, PAUSE_STAKE, PAUSE_UNSTAKE},
+};
+
+const PAUSE_NAMES: [(u8, &str); 5] = [
+    (PAUSE_STAKE, "stake"),
+    (PAUSE_UNSTAKE, "unstake"),
+    (PAUSE_CLAIM, "claim"),
+    (PAUSE_REFERRAL_CLAIM, "referral-claim"),
+    (PAUSE_REFERRAL_LINK, "referral-link"),
+];
+
+pub fn print_pool(keys: &PoolKeys, pool: &StakingPool) {
+    section("Pool");
+    row("address", keys.pool);
+    row("pool id", pool.pool_id);
+    row("version", pool.version);
+    row("authority", pool.authority);
+    row("stake token program", keys.staking_token_program);
+    row("reward token program", keys.reward_token_program);
+
+    section("Tokens");
+    row("token mint", pool.token_mint);
+    row("reward mint", pool.reward_mint);
+    row("staking vault", pool.staking_vault);
+    row("reward vault", pool.reward_vault);
+    row("restake pool", optional_key(&pool.restake_pool));
+
+    section("Rewards");
+    row("reward rate", format!("{} / s", pool.reward_rate));
+    row("total staked", pool.total_staked);
+    row("reward liability", pool.reward_liability);
+    row("reward per token", pool.reward_per_token_stored);
+    row("last update", time(pool.last_update_time));
+
+    section("Schedule");
+    row("start", if pool.start_time == 0 { "immediately".to_string() } else { time(pool.start_time) });
+    row("end", if pool.end_time == 0 { "never".to_string() } else { time(pool.end_time) });
+    row("lock duration", duration(pool.lock_duration));
+    row("sunset", pool.sunset);
+    row("emergency mode", pool.emergency_mode);
+    row("paused", paused(pool.pause_flags));
+
+    section("Limits");
+    row("min stake", pool.min_stake_amount);
+    row("max per wallet", limit(pool.max_stake_per_wallet));
+    row("max total", limit(pool.max_total_staked));
+    row("allowlist", if pool.has_allowlist() { hex(&pool.allowlist_root) } else { "off".to_string() });
+
+    section("Fees");
+    row("stake fee", format!("{} lamports", pool.fee_stake));
+    row("unstake fee", format!("{} lamports", pool.fee_unstake));
+    row("claim fee", format!("{} lamports", pool.fee_claim));
+    row("fee receiver", pool.fee_receiver);
+
+    section("Referral");
+    row("enabled", pool.referral_enabled);
+    row("rates L1/L2/L3", format!("{} / {} / {}", bps(pool.referral_l1_bps), bps(pool.referral_l2_bps), bps(pool.referral_l3_bps)));
+    row("strict accounts", pool.strict_referrer_accounts);
+    row("min referee stake", pool.min_referee_stake);
+    row("min referrer age", duration(pool.min_referrer_stake_age));
+    row("vesting cliff", duration(pool.commission_vesting_cliff));
+    row("vesting duration", duration(pool.commission_vesting_duration));
+}
+
+fn section(title: &str) {
+    println!("\n{title}");
+}
+
+fn row(label: &str, value: impl std::fmt::Display) {
+    println!("  {label:<20} {value}");
+}
+
+fn optional_key(key: &solana_sdk::pubkey::Pubkey) -> String {
+    if *key == Default::default() { "none".to_string() } else { key.to_string() }
+}
+
+fn limit(value: u64) -> String {
+    if value == 0 { "unlimited".to_string() } else { value.to_string() }
+}
+
+fn bps(value: u16) -> String {
+    format!("{}.{:02}%", value / 100, value % 100)
+}
+
+fn paused(flags: u8) -> String {
+    let names: Vec<&str> = PAUSE_NAMES.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, name)| *name).collect();
+    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+}
+
+/// UTC date-time of a unix timestamp (days-to-civil conversion, proleptic Gregorian).
+fn time(unix: i64) -> String {
+    let (days, secs) = (unix.div_euclid(86_400), unix.rem_euclid(86_400));
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC ({unix})",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn duration(seconds: i64) -> String {
+    if seconds == 0 {
+        return "0".to_string();
+    }
+    let (days, rem) = (seconds / 86_400, seconds % 86_400);
+    let (hours, rem) = (rem / 3_600, rem % 3_600);
+    let (minutes, secs) = (rem / 60, rem % 60);
+    let mut parts = Vec::new();
+    for (value, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (secs, "s")] {
+        if value != 0 {
+            parts.push(format!("{value}{unit}"));
+        }
+    }
+    format!("{} ({seconds}s)", parts.join(" "))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
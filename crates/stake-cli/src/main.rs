@@ -0,0 +1,285 @@
+//! `stake-admin`: the admin panel's operations from the command line.
+//!
+//! Every transaction command signs with `--keypair` (the pool authority, or the funder for
+//! `add-rewards`). `--dry-run` simulates the transaction unsigned instead, printing logs and decoded
+//! events, so a hardware wallet isn't asked to sign anything. The signer accepts anything the Solana
+//! CLI does: a keypair file, `usb://ledger`, `prompt://` or `stdin`.
+
+mod display;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use solana_clap_utils::keypair::signer_from_path;
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::Transaction,
+};
+use stake_client::{
+    events::{parse_logs, StakeEvent},
+    instruction::{self, PoolKeys},
+    pda, rpc,
+    state::PoolRegistry,
+};
+
+#[derive(Parser)]
+#[command(name = "stake-admin", version, about = "Admin CLI for the referral staking program")]
+struct Cli {
+    /// RPC endpoint
+    #[arg(long, short = 'u', global = true, env = "STAKE_RPC_URL", default_value = "https://api.devnet.solana.com")]
+    url: String,
+    /// Signer: keypair file, `usb://ledger`, `prompt://` or `stdin` (defaults to the Solana CLI keypair)
+    #[arg(long, short = 'k', global = true, env = "STAKE_KEYPAIR")]
+    keypair: Option<String>,
+    /// Staking program id
+    #[arg(long, global = true, env = "STAKE_PROGRAM_ID", default_value_t = stake_client::PROGRAM_ID)]
+    program_id: Pubkey,
+    /// Simulate without signing instead of sending
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a pool (and the pool registry, if it doesn't exist yet, which only the upgrade authority can)
+    Initialize {
+        #[arg(long)]
+        token_mint: Pubkey,
+        #[arg(long)]
+        reward_mint: Pubkey,
+        /// Reward tokens (base units) emitted per second across the pool
+        #[arg(long)]
+        reward_rate: u64,
+        #[arg(long)]
+        name: String,
+        /// Unix time stakes open (0 = immediately)
+        #[arg(long, default_value_t = 0)]
+        start_time: i64,
+        /// Unix time accrual stops (0 = never)
+        #[arg(long, default_value_t = 0)]
+        end_time: i64,
+        #[arg(long)]
+        disable_referral: bool,
+    },
+    /// Set the flat stake/unstake/claim fees (lamports) and their receiver
+    UpdateFees {
+        #[command(flatten)]
+        pool: PoolArg,
+        #[arg(long)]
+        fee_stake: u64,
+        #[arg(long)]
+        fee_unstake: u64,
+        #[arg(long)]
+        fee_claim: u64,
+        /// Defaults to the current fee receiver
+        #[arg(long)]
+        receiver: Option<Pubkey>,
+    },
+    /// Commission per level, in basis points
+    UpdateReferralRates {
+        #[command(flatten)]
+        pool: PoolArg,
+        l1: u16,
+        l2: u16,
+        l3: u16,
+    },
+    /// Set the lock applied to new stakes
+    UpdateLockDuration {
+        #[command(flatten)]
+        pool: PoolArg,
+        /// Seconds
+        duration: i64,
+    },
+    /// Set the pool-wide emission rate
+    UpdateRewardRate {
+        #[command(flatten)]
+        pool: PoolArg,
+        /// Reward tokens (base units) per second
+        rate: u64,
+    },
+    /// Pause or resume every operation at once
+    SetPauseState {
+        #[command(flatten)]
+        pool: PoolArg,
+        #[arg(action = clap::ArgAction::Set)]
+        paused: bool,
+    },
+    /// Enable or disable referral linking and commission
+    ToggleReferralSystem {
+        #[command(flatten)]
+        pool: PoolArg,
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Withdraw the staking vault's surplus above staked tokens and owed rewards
+    WithdrawTokens {
+        #[command(flatten)]
+        pool: PoolArg,
+        amount: u64,
+        /// Defaults to the signer's associated token account
+        #[arg(long)]
+        destination: Option<Pubkey>,
+    },
+    /// Fund the reward vault from the signer
+    AddRewards {
+        #[command(flatten)]
+        pool: PoolArg,
+        amount: u64,
+        /// Defaults to the signer's associated token account
+        #[arg(long)]
+        source: Option<Pubkey>,
+    },
+    /// Read-only pool inspection
+    #[command(subcommand)]
+    Pool(PoolCommand),
+}
+
+#[derive(Subcommand)]
+enum PoolCommand {
+    /// Print the decoded `StakingPool` account
+    Show {
+        #[command(flatten)]
+        pool: PoolArg,
+    },
+}
+
+#[derive(Args)]
+struct PoolArg {
+    /// StakingPool address
+    #[arg(long, short = 'p', env = "STAKE_POOL")]
+    pool: Pubkey,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let client = RpcClient::new_with_commitment(cli.url.clone(), CommitmentConfig::confirmed());
+    let program_id = cli.program_id;
+
+    match &cli.command {
+        Command::Pool(PoolCommand::Show { pool }) => {
+            let (keys, state) = rpc::fetch_pool_keys(&client, &program_id, &pool.pool)?;
+            display::print_pool(&keys, &state);
+            return Ok(());
+        }
+        Command::Initialize { token_mint, reward_mint, reward_rate, name, start_time, end_time, disable_referral } => {
+            let signer = load_signer(&cli)?;
+            let staking_token_program = mint_owner(&client, token_mint)?;
+            let reward_token_program = mint_owner(&client, reward_mint)?;
+            let registry_key = pda::pool_registry(&program_id).0;
+            let mut instructions = Vec::new();
+            let pool_id = match rpc::fetch_optional::<PoolRegistry>(&client, &registry_key)? {
+                Some(registry) => registry.pool_count,
+                None => {
+                    instructions.push(instruction::initialize_registry(program_id, signer.pubkey()));
+                    0
+                }
+            };
+            let keys = PoolKeys::derive(program_id, *token_mint, *reward_mint, pool_id, signer.pubkey(), staking_token_program, reward_token_program);
+            instructions.push(instruction::initialize(&keys, *reward_rate, !disable_referral, name.clone(), *start_time, *end_time));
+            println!("Pool #{pool_id}: {}", keys.pool);
+            return submit(&client, &cli, signer.as_ref(), &instructions);
+        }
+        _ => {}
+    }
+
+    let signer = load_signer(&cli)?;
+    let pool = match &cli.command {
+        Command::UpdateFees { pool, .. }
+        | Command::UpdateReferralRates { pool, .. }
+        | Command::UpdateLockDuration { pool, .. }
+        | Command::UpdateRewardRate { pool, .. }
+        | Command::SetPauseState { pool, .. }
+        | Command::ToggleReferralSystem { pool, .. }
+        | Command::WithdrawTokens { pool, .. }
+        | Command::AddRewards { pool, .. } => pool.pool,
+        Command::Initialize { .. } | Command::Pool(_) => unreachable!("handled above"),
+    };
+    let (keys, state) = rpc::fetch_pool_keys(&client, &program_id, &pool)?;
+    if !matches!(cli.command, Command::AddRewards { .. }) && signer.pubkey() != state.authority {
+        bail!("{} is not the pool authority ({})", signer.pubkey(), state.authority);
+    }
+
+    let ix = match &cli.command {
+        Command::UpdateFees { fee_stake, fee_unstake, fee_claim, receiver, .. } => {
+            instruction::update_fees(&keys, *fee_stake, *fee_unstake, *fee_claim, receiver.unwrap_or(state.fee_receiver))
+        }
+        Command::UpdateReferralRates { l1, l2, l3, .. } => instruction::update_referral_rates(&keys, *l1, *l2, *l3),
+        Command::UpdateLockDuration { duration, .. } => instruction::update_lock_duration(&keys, *duration),
+        Command::UpdateRewardRate { rate, .. } => instruction::update_reward_rate(&keys, *rate),
+        Command::SetPauseState { paused, .. } => instruction::set_pause_state(&keys, *paused),
+        Command::ToggleReferralSystem { enabled, .. } => instruction::toggle_referral_system(&keys, *enabled),
+        Command::WithdrawTokens { amount, destination, .. } => {
+            let destination = destination.unwrap_or_else(|| pda::associated_token_account(&signer.pubkey(), &state.token_mint, &keys.staking_token_program));
+            instruction::withdraw_tokens(&keys, destination, *amount)
+        }
+        Command::AddRewards { amount, source, .. } => {
+            let source = source.unwrap_or_else(|| pda::associated_token_account(&signer.pubkey(), &state.reward_mint, &keys.reward_token_program));
+            instruction::add_rewards(&keys, signer.pubkey(), source, *amount)
+        }
+        Command::Initialize { .. } | Command::Pool(_) => unreachable!("handled above"),
+    };
+    submit(&client, &cli, signer.as_ref(), &[ix])
+}
+
+fn load_signer(cli: &Cli) -> Result<Box<dyn Signer>> {
+    let path = match &cli.keypair {
+        Some(path) => path.clone(),
+        None => {
+            let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set; pass --keypair"))?;
+            PathBuf::from(home).join(".config/solana/id.json").display().to_string()
+        }
+    };
+    signer_from_path(&clap_v2::ArgMatches::default(), &path, "keypair", &mut None).map_err(|err| anyhow!("loading signer {path}: {err}"))
+}
+
+fn mint_owner(client: &RpcClient, mint: &Pubkey) -> Result<Pubkey> {
+    Ok(client.get_account(mint).with_context(|| format!("fetching mint {mint}"))?.owner)
+}
+
+fn submit(client: &RpcClient, cli: &Cli, signer: &dyn Signer, instructions: &[Instruction]) -> Result<()> {
+    if cli.dry_run {
+        let tx = Transaction::new_unsigned(Message::new(instructions, Some(&signer.pubkey())));
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(client.commitment()),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let result = client.simulate_transaction_with_config(&tx, config)?.value;
+        let logs = result.logs.unwrap_or_default();
+        for line in &logs {
+            println!("  {line}");
+        }
+        print_events(&parse_logs(&cli.program_id, &logs));
+        if let Some(units) = result.units_consumed {
+            println!("Compute units: {units}");
+        }
+        return match result.err {
+            Some(err) => Err(anyhow!("simulation failed: {err}")),
+            None => {
+                println!("Simulation succeeded (not sent)");
+                Ok(())
+            }
+        };
+    }
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&signer.pubkey()), &[signer], blockhash);
+    let signature = client.send_and_confirm_transaction_with_spinner(&tx)?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+fn print_events(events: &[StakeEvent]) {
+    for event in events {
+        println!("Event {}: {event:?}", event.name());
+    }
+}
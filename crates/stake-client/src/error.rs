@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("account data is not a {0}")]
+    DiscriminatorMismatch(&'static str),
+    #[error("failed to deserialize {0}: {1}")]
+    Deserialize(&'static str, std::io::Error),
+    #[error("account {0} not found")]
+    AccountNotFound(solana_program::pubkey::Pubkey),
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Rpc(Box<solana_client::client_error::ClientError>),
+}
+
+#[cfg(feature = "rpc")]
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
@@ -0,0 +1,450 @@
+//! Mirrors of the program's Anchor events and a log parser for them.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::discriminator;
+use crate::state::PoolStatus;
+
+macro_rules! events {
+    ($($name:ident { $($field:ident: $ty:ty,)* })*) => {
+        $(
+            #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+            pub struct $name {
+                $(pub $field: $ty,)*
+            }
+        )*
+
+        /// Any event the program emits.
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum StakeEvent {
+            $($name($name),)*
+        }
+
+        impl StakeEvent {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(StakeEvent::$name(_) => stringify!($name),)*
+                }
+            }
+
+            /// Decodes one `Program data:` payload (8-byte discriminator + borsh body).
+            /// Returns `None` for payloads that aren't one of this program's events.
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                if data.len() < 8 {
+                    return None;
+                }
+                let (disc, mut body) = data.split_at(8);
+                $(
+                    if disc == discriminator("event", stringify!($name)) {
+                        return $name::deserialize(&mut body).ok().map(StakeEvent::$name);
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+events! {
+    RegistryInitialized {
+        registry: Pubkey,
+        authority: Pubkey,
+        timestamp: i64,
+    }
+    PoolInitialized {
+        pool: Pubkey,
+        pool_id: u64,
+        authority: Pubkey,
+        token_mint: Pubkey,
+        reward_mint: Pubkey,
+        reward_rate: u64,
+        referral_enabled: bool,
+        start_time: i64,
+        end_time: i64,
+        timestamp: i64,
+    }
+    NewReferral {
+        user: Pubkey,
+        pool: Pubkey,
+        referrer: Pubkey,
+        referrer_l2: Option<Pubkey>,
+        referrer_l3: Option<Pubkey>,
+        timestamp: i64,
+    }
+    Staked {
+        user: Pubkey,
+        pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        user_staked: u64,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    Unstaked {
+        user: Pubkey,
+        pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        user_staked: u64,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    RewardsClaimed {
+        user: Pubkey,
+        pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    CommissionCredited {
+        pool: Pubkey,
+        referee: Pubkey,
+        reward: u64,
+        l1_referrer: Option<Pubkey>,
+        l1_amount: u64,
+        l2_referrer: Option<Pubkey>,
+        l2_amount: u64,
+        l3_referrer: Option<Pubkey>,
+        l3_amount: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    ReferralRewardsClaimed {
+        referrer: Pubkey,
+        pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    ReferrerStatsInitialized {
+        referrer: Pubkey,
+        pool: Pubkey,
+        referrer_stats: Pubkey,
+        timestamp: i64,
+    }
+    ReferrerFlagUpdated {
+        referrer: Pubkey,
+        pool: Pubkey,
+        old_flagged: bool,
+        new_flagged: bool,
+        timestamp: i64,
+    }
+    FeesUpdated {
+        pool: Pubkey,
+        old_fee_stake: u64,
+        new_fee_stake: u64,
+        old_fee_unstake: u64,
+        new_fee_unstake: u64,
+        old_fee_claim: u64,
+        new_fee_claim: u64,
+        old_fee_receiver: Pubkey,
+        new_fee_receiver: Pubkey,
+        timestamp: i64,
+    }
+    ReferralRatesUpdated {
+        pool: Pubkey,
+        old_l1_bps: u16,
+        new_l1_bps: u16,
+        old_l2_bps: u16,
+        new_l2_bps: u16,
+        old_l3_bps: u16,
+        new_l3_bps: u16,
+        timestamp: i64,
+    }
+    LockDurationUpdated {
+        pool: Pubkey,
+        old_duration: i64,
+        new_duration: i64,
+        timestamp: i64,
+    }
+    RewardRateUpdated {
+        pool: Pubkey,
+        old_rate: u64,
+        new_rate: u64,
+        reward_per_token_stored: u128,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    EmergencyModeUpdated {
+        pool: Pubkey,
+        old_enabled: bool,
+        new_enabled: bool,
+        timestamp: i64,
+    }
+    PoolMetadataUpdated {
+        pool: Pubkey,
+        name: String,
+        old_start_time: i64,
+        new_start_time: i64,
+        old_end_time: i64,
+        new_end_time: i64,
+        old_status: PoolStatus,
+        new_status: PoolStatus,
+        timestamp: i64,
+    }
+    PoolSunsetStarted {
+        pool: Pubkey,
+        end_time: i64,
+        total_staked: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    PoolClosed {
+        pool: Pubkey,
+        authority: Pubkey,
+        swept_stake_tokens: u64,
+        swept_reward_tokens: u64,
+        timestamp: i64,
+    }
+    StakeLimitsUpdated {
+        pool: Pubkey,
+        old_max_stake_per_wallet: u64,
+        new_max_stake_per_wallet: u64,
+        old_max_total_staked: u64,
+        new_max_total_staked: u64,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    AllowlistRootUpdated {
+        pool: Pubkey,
+        old_root: [u8; 32],
+        new_root: [u8; 32],
+        timestamp: i64,
+    }
+    RestakePoolUpdated {
+        pool: Pubkey,
+        old_restake_pool: Pubkey,
+        new_restake_pool: Pubkey,
+        timestamp: i64,
+    }
+    CommissionVestingUpdated {
+        pool: Pubkey,
+        old_cliff: i64,
+        new_cliff: i64,
+        old_duration: i64,
+        new_duration: i64,
+        timestamp: i64,
+    }
+    ReferralGuardsUpdated {
+        pool: Pubkey,
+        old_min_referee_stake: u64,
+        new_min_referee_stake: u64,
+        old_min_referrer_stake_age: i64,
+        new_min_referrer_stake_age: i64,
+        timestamp: i64,
+    }
+    ReferralSystemToggled {
+        pool: Pubkey,
+        old_enabled: bool,
+        new_enabled: bool,
+        timestamp: i64,
+    }
+    StrictReferrerAccountsUpdated {
+        pool: Pubkey,
+        old_enabled: bool,
+        new_enabled: bool,
+        timestamp: i64,
+    }
+    TokensWithdrawn {
+        pool: Pubkey,
+        authority: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        vault_balance: u64,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    RewardsAdded {
+        pool: Pubkey,
+        funder: Pubkey,
+        amount: u64,
+        reward_vault_balance: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    UserStakeClosed {
+        user: Pubkey,
+        pool: Pubkey,
+        user_stake: Pubkey,
+        timestamp: i64,
+    }
+    ReferrerStatsClosed {
+        referrer: Pubkey,
+        pool: Pubkey,
+        referrer_stats: Pubkey,
+        total_referrals: u64,
+        total_commission_earned: u64,
+        timestamp: i64,
+    }
+    AccountMigrated {
+        account: Pubkey,
+        pool: Pubkey,
+        old_version: u8,
+        new_version: u8,
+        data_len: u64,
+        timestamp: i64,
+    }
+    PauseFlagsUpdated {
+        pool: Pubkey,
+        old_flags: u8,
+        new_flags: u8,
+        staking_paused: bool,
+        unstaking_paused: bool,
+        claims_paused: bool,
+        referral_claims_paused: bool,
+        referral_linking_paused: bool,
+        timestamp: i64,
+    }
+    EmergencyWithdrawn {
+        user: Pubkey,
+        pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        forfeited_rewards: u64,
+        total_staked: u64,
+        timestamp: i64,
+    }
+    RewardsRestaked {
+        user: Pubkey,
+        from_pool: Pubkey,
+        to_pool: Pubkey,
+        amount: u64,
+        net_amount: u64,
+        timestamp: i64,
+    }
+    AllRewardsClaimed {
+        user: Pubkey,
+        pool: Pubkey,
+        staking_reward: u64,
+        referral_reward: u64,
+        total: u64,
+        net_total: u64,
+        timestamp: i64,
+    }
+    CommissionVested {
+        referrer: Pubkey,
+        pool: Pubkey,
+        grant: Pubkey,
+        amount: u64,
+        cliff_time: i64,
+        end_time: i64,
+        pending_rewards: u64,
+        grant_count: u64,
+        timestamp: i64,
+    }
+    CommissionReleased {
+        referrer: Pubkey,
+        pool: Pubkey,
+        grant: Pubkey,
+        amount: u64,
+        released_amount: u64,
+        remaining_amount: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    CommissionClawedBack {
+        referrer: Pubkey,
+        pool: Pubkey,
+        grant: Pubkey,
+        amount: u64,
+        total_amount: u64,
+        released_amount: u64,
+        reward_liability: u64,
+        timestamp: i64,
+    }
+    AccountFrozen {
+        account: Pubkey,
+        owner: Pubkey,
+        pool: Pubkey,
+        reason: u8,
+        timestamp: i64,
+    }
+    AccountUnfrozen {
+        account: Pubkey,
+        owner: Pubkey,
+        pool: Pubkey,
+        reason: u8,
+        timestamp: i64,
+    }
+    CompetitionCreated {
+        competition: Pubkey,
+        pool: Pubkey,
+        round_id: u64,
+        start_time: i64,
+        end_time: i64,
+        prize_split_bps: Vec<u16>,
+        timestamp: i64,
+    }
+    CompetitionFunded {
+        competition: Pubkey,
+        funder: Pubkey,
+        amount: u64,
+        prize_pool: u64,
+        timestamp: i64,
+    }
+    CompetitionSettled {
+        competition: Pubkey,
+        pool: Pubkey,
+        prize_pool: u64,
+        winners: u8,
+        total_volume: u64,
+        timestamp: i64,
+    }
+    CompetitionPrizePaid {
+        competition: Pubkey,
+        referrer: Pubkey,
+        rank: u8,
+        volume: u64,
+        amount: u64,
+        timestamp: i64,
+    }
+    CompetitionEntered {
+        competition: Pubkey,
+        referrer: Pubkey,
+        competition_entry: Pubkey,
+        timestamp: i64,
+    }
+    CompetitionRemainderSwept {
+        competition: Pubkey,
+        pool: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        timestamp: i64,
+    }
+}
+
+/// Decodes every event `program_id` emitted in a transaction's log messages, in order.
+/// `Program data:` lines logged while another program is executing (CPI targets) are skipped.
+pub fn parse_logs(program_id: &Pubkey, logs: &[String]) -> Vec<StakeEvent> {
+    let program = program_id.to_string();
+    let mut invoke_stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(payload) = rest.strip_prefix("data: ") {
+            if invoke_stack.last() == Some(&program.as_str()) {
+                if let Some(event) = STANDARD.decode(payload).ok().and_then(|bytes| StakeEvent::decode(&bytes)) {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+
+        let mut parts = rest.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(id), Some("invoke")) => invoke_stack.push(id),
+            (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                invoke_stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
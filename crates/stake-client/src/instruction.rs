@@ -0,0 +1,712 @@
+//! Instruction builders. Account order matches the program's `#[derive(Accounts)]` structs;
+//! an omitted optional account is passed as the program id, as Anchor expects.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+use crate::{discriminator, pda, state::StakingPool, upline::LinkAccounts, upline::Upline};
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AllowlistProof {
+    pub cap: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimDestination {
+    Wallet,
+    RestakeThisPool,
+    RestakeOtherPool,
+}
+
+/// Addresses every pool-scoped instruction needs, usually read once from the `StakingPool` account.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolKeys {
+    pub program_id: Pubkey,
+    pub pool: Pubkey,
+    pub pool_id: u64,
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub fee_receiver: Pubkey,
+    /// Owners of `token_mint` and `reward_mint` (SPL Token or Token-2022); they may differ.
+    pub staking_token_program: Pubkey,
+    pub reward_token_program: Pubkey,
+}
+
+impl PoolKeys {
+    pub fn from_state(program_id: Pubkey, pool: Pubkey, state: &StakingPool, staking_token_program: Pubkey, reward_token_program: Pubkey) -> Self {
+        Self {
+            program_id,
+            pool,
+            pool_id: state.pool_id,
+            authority: state.authority,
+            token_mint: state.token_mint,
+            reward_mint: state.reward_mint,
+            staking_vault: state.staking_vault,
+            reward_vault: state.reward_vault,
+            fee_receiver: state.fee_receiver,
+            staking_token_program,
+            reward_token_program,
+        }
+    }
+
+    /// Keys of a pool that `initialize` is about to create; `fee_receiver` starts as the authority.
+    pub fn derive(
+        program_id: Pubkey,
+        token_mint: Pubkey,
+        reward_mint: Pubkey,
+        pool_id: u64,
+        authority: Pubkey,
+        staking_token_program: Pubkey,
+        reward_token_program: Pubkey,
+    ) -> Self {
+        let pool = pda::staking_pool(&program_id, &token_mint, pool_id).0;
+        Self {
+            program_id,
+            pool,
+            pool_id,
+            authority,
+            token_mint,
+            reward_mint,
+            staking_vault: pda::staking_vault(&program_id, &pool).0,
+            reward_vault: pda::reward_vault(&program_id, &pool).0,
+            fee_receiver: authority,
+            staking_token_program,
+            reward_token_program,
+        }
+    }
+
+    pub fn user_stake(&self, user: &Pubkey) -> Pubkey {
+        pda::user_stake(&self.program_id, user, &self.pool).0
+    }
+
+    pub fn pool_entry(&self) -> Pubkey {
+        pda::pool_entry(&self.program_id, self.pool_id).0
+    }
+
+    pub fn referrer_stats(&self, referrer: &Pubkey) -> Pubkey {
+        pda::referrer_stats(&self.program_id, &self.pool, referrer).0
+    }
+
+    fn optional(&self, key: Option<Pubkey>) -> AccountMeta {
+        match key {
+            Some(key) => AccountMeta::new(key, false),
+            None => AccountMeta::new_readonly(self.program_id, false),
+        }
+    }
+
+    fn admin(&self, name: &str, args: impl BorshSerialize) -> Instruction {
+        build(
+            self.program_id,
+            name,
+            args,
+            vec![AccountMeta::new(self.pool, false), AccountMeta::new_readonly(self.authority, true)],
+        )
+    }
+}
+
+fn build(program_id: Pubkey, name: &str, args: impl BorshSerialize, accounts: Vec<AccountMeta>) -> Instruction {
+    let mut data = discriminator("global", name).to_vec();
+    args.serialize(&mut data).expect("serializing into a Vec cannot fail");
+    Instruction { program_id, accounts, data }
+}
+
+/// `authority` has to be the program's upgrade authority.
+pub fn initialize_registry(program_id: Pubkey, authority: Pubkey) -> Instruction {
+    build(
+        program_id,
+        "initialize_registry",
+        (),
+        vec![
+            AccountMeta::new(pda::pool_registry(&program_id).0, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(pda::program_data(&program_id), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// `keys` should come from [`PoolKeys::derive`] with the registry's current `pool_count` as `pool_id`.
+pub fn initialize(keys: &PoolKeys, reward_rate: u64, enable_referral: bool, name: String, start_time: i64, end_time: i64) -> Instruction {
+    build(
+        keys.program_id,
+        "initialize",
+        (reward_rate, enable_referral, name, start_time, end_time),
+        vec![
+            AccountMeta::new(pda::pool_registry(&keys.program_id).0, false),
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.pool_entry(), false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(keys.staking_vault, false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(keys.authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(keys.staking_token_program, false),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+    )
+}
+
+/// `user_token_account` is `None` to stake native SOL straight from the wallet.
+/// `link` carries the referrer accounts: [`LinkAccounts::resolve`] on a first stake with a referrer,
+/// [`LinkAccounts::existing`] for top-ups of a linked stake. `competition` is the pool's
+/// `active_competition` with the L1 referrer's [`pda::competition_entry`] in it, required while one is set.
+#[allow(clippy::too_many_arguments)]
+pub fn stake_with_referral(
+    keys: &PoolKeys,
+    user: Pubkey,
+    user_token_account: Option<Pubkey>,
+    amount: u64,
+    referrer: Option<Pubkey>,
+    allowlist_proof: Option<AllowlistProof>,
+    link: &LinkAccounts,
+    competition: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    build(
+        keys.program_id,
+        "stake_with_referral",
+        (amount, referrer, allowlist_proof),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.user_stake(&user), false),
+            AccountMeta::new(keys.staking_vault, false),
+            keys.optional(user_token_account),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new(keys.fee_receiver, false),
+            keys.optional(link.referrer_stats),
+            keys.optional(link.referrer_user_stake),
+            keys.optional(link.referrer_stats_l2),
+            keys.optional(link.l2_user_stake),
+            keys.optional(link.referrer_stats_l3),
+            keys.optional(competition.map(|(competition, _)| competition)),
+            keys.optional(competition.map(|(_, entry)| entry)),
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(keys.staking_token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Where `claim_rewards` restakes to; `None` pays out to the wallet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestakeTarget {
+    ThisPool,
+    /// The pool's configured `restake_pool`, with its staking vault and the user's upline there. The user
+    /// must already have a stake in that pool; `claim_rewards` doesn't create one.
+    OtherPool { pool: Pubkey, staking_vault: Pubkey, upline: Upline },
+}
+
+pub fn claim_rewards(keys: &PoolKeys, user: Pubkey, user_reward_account: Pubkey, upline: &Upline, restake: Option<RestakeTarget>) -> Instruction {
+    let (destination, staking_vault, target_pool, target_user_stake, target_staking_vault, target_upline) = match restake {
+        None => (ClaimDestination::Wallet, None, None, None, None, Upline::default()),
+        Some(RestakeTarget::ThisPool) => (ClaimDestination::RestakeThisPool, Some(keys.staking_vault), None, None, None, Upline::default()),
+        Some(RestakeTarget::OtherPool { pool, staking_vault, upline }) => (
+            ClaimDestination::RestakeOtherPool,
+            None,
+            Some(pool),
+            Some(pda::user_stake(&keys.program_id, &user, &pool).0),
+            Some(staking_vault),
+            upline,
+        ),
+    };
+    build(
+        keys.program_id,
+        "claim_rewards",
+        destination,
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.user_stake(&user), false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(user_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(keys.fee_receiver, false),
+            keys.optional(upline.l1),
+            keys.optional(upline.l2),
+            keys.optional(upline.l3),
+            keys.optional(staking_vault),
+            keys.optional(target_pool),
+            keys.optional(target_user_stake),
+            keys.optional(target_staking_vault),
+            keys.optional(target_upline.l1),
+            keys.optional(target_upline.l2),
+            keys.optional(target_upline.l3),
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// `include_referral` adds the user's own `ReferrerStats` so its commission is paid in the same transfer.
+pub fn claim_all(keys: &PoolKeys, user: Pubkey, user_reward_account: Pubkey, upline: &Upline, include_referral: bool) -> Instruction {
+    let own_stats = include_referral.then(|| keys.referrer_stats(&user));
+    build(
+        keys.program_id,
+        "claim_all",
+        (),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.user_stake(&user), false),
+            keys.optional(own_stats),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(user_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            keys.optional(upline.l1),
+            keys.optional(upline.l2),
+            keys.optional(upline.l3),
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+pub fn claim_referral_rewards(keys: &PoolKeys, referrer: Pubkey, referrer_reward_account: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "claim_referral_rewards",
+        (),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.referrer_stats(&referrer), false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(referrer_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(referrer, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+/// `grant_index` is the current `grant_count` of the referrer's `ReferrerStats` in this pool.
+pub fn vest_referral_rewards(keys: &PoolKeys, referrer: Pubkey, grant_index: u64) -> Instruction {
+    build(
+        keys.program_id,
+        "vest_referral_rewards",
+        (),
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new(keys.referrer_stats(&referrer), false),
+            AccountMeta::new(pda::commission_grant(&keys.program_id, &keys.pool, &referrer, grant_index).0, false),
+            AccountMeta::new(referrer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn release_vested_commission(keys: &PoolKeys, referrer: Pubkey, grant: Pubkey, referrer_reward_account: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "release_vested_commission",
+        (),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(grant, false),
+            AccountMeta::new(keys.referrer_stats(&referrer), false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(referrer_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(referrer, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+fn flag_referrer_accounts(keys: &PoolKeys, referrer: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(keys.pool, false),
+        AccountMeta::new(keys.referrer_stats(referrer), false),
+        AccountMeta::new_readonly(keys.authority, true),
+    ]
+}
+
+pub fn flag_referrer(keys: &PoolKeys, referrer: Pubkey, flagged: bool) -> Instruction {
+    build(keys.program_id, "flag_referrer", flagged, flag_referrer_accounts(keys, &referrer))
+}
+
+pub fn set_referrer_frozen(keys: &PoolKeys, referrer: Pubkey, frozen: bool, reason: u8) -> Instruction {
+    build(keys.program_id, "set_referrer_frozen", (frozen, reason), flag_referrer_accounts(keys, &referrer))
+}
+
+pub fn set_user_stake_frozen(keys: &PoolKeys, user: Pubkey, frozen: bool, block_unstake: bool, reason: u8) -> Instruction {
+    build(
+        keys.program_id,
+        "set_user_stake_frozen",
+        (frozen, block_unstake, reason),
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new(keys.user_stake(&user), false),
+            AccountMeta::new_readonly(keys.authority, true),
+        ],
+    )
+}
+
+pub fn clawback_commission_grant(keys: &PoolKeys, referrer: Pubkey, grant: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "clawback_commission_grant",
+        (),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.referrer_stats(&referrer), false),
+            AccountMeta::new(grant, false),
+            AccountMeta::new(referrer, false),
+            AccountMeta::new_readonly(keys.authority, true),
+        ],
+    )
+}
+
+/// Shared head of `unstake` / `emergency_withdraw`. `user_token_account` is `None` to unwrap a
+/// native pool back to SOL through the user's `native_unwrap` PDA.
+fn withdraw_accounts(keys: &PoolKeys, user: &Pubkey, user_token_account: Option<Pubkey>) -> Vec<AccountMeta> {
+    let native_unwrap = user_token_account.is_none().then(|| pda::native_unwrap(&keys.program_id, user).0);
+    vec![
+        AccountMeta::new(keys.pool, false),
+        AccountMeta::new(keys.user_stake(user), false),
+        AccountMeta::new(keys.staking_vault, false),
+        keys.optional(user_token_account),
+        keys.optional(native_unwrap),
+        AccountMeta::new_readonly(keys.token_mint, false),
+    ]
+}
+
+fn withdraw_tail(keys: &PoolKeys, accounts: &mut Vec<AccountMeta>, user: Pubkey, upline: &Upline) {
+    accounts.extend([
+        keys.optional(upline.l1),
+        keys.optional(upline.l2),
+        keys.optional(upline.l3),
+        AccountMeta::new(user, true),
+        AccountMeta::new_readonly(keys.staking_token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]);
+}
+
+pub fn unstake(keys: &PoolKeys, user: Pubkey, user_token_account: Option<Pubkey>, upline: &Upline, amount: u64) -> Instruction {
+    let mut accounts = withdraw_accounts(keys, &user, user_token_account);
+    accounts.push(AccountMeta::new(keys.fee_receiver, false));
+    withdraw_tail(keys, &mut accounts, user, upline);
+    build(keys.program_id, "unstake", amount, accounts)
+}
+
+pub fn emergency_withdraw(keys: &PoolKeys, user: Pubkey, user_token_account: Option<Pubkey>, upline: &Upline) -> Instruction {
+    let mut accounts = withdraw_accounts(keys, &user, user_token_account);
+    withdraw_tail(keys, &mut accounts, user, upline);
+    build(keys.program_id, "emergency_withdraw", (), accounts)
+}
+
+pub fn set_emergency_mode(keys: &PoolKeys, enabled: bool) -> Instruction {
+    keys.admin("set_emergency_mode", enabled)
+}
+
+pub fn update_fees(keys: &PoolKeys, fee_stake: u64, fee_unstake: u64, fee_claim: u64, new_receiver: Pubkey) -> Instruction {
+    keys.admin("update_fees", (fee_stake, fee_unstake, fee_claim, new_receiver))
+}
+
+pub fn update_referral_rates(keys: &PoolKeys, l1: u16, l2: u16, l3: u16) -> Instruction {
+    keys.admin("update_referral_rates", (l1, l2, l3))
+}
+
+pub fn update_lock_duration(keys: &PoolKeys, duration: i64) -> Instruction {
+    keys.admin("update_lock_duration", duration)
+}
+
+pub fn update_reward_rate(keys: &PoolKeys, new_rate: u64) -> Instruction {
+    keys.admin("update_reward_rate", new_rate)
+}
+
+pub fn set_pause_state(keys: &PoolKeys, paused: bool) -> Instruction {
+    build(keys.program_id, "set_pause_state", paused, pool_metadata_accounts(keys))
+}
+
+/// Sets (`paused = true`) or clears the `PAUSE_*` bits in `flags`, leaving the others as they are.
+pub fn set_pause_flags(keys: &PoolKeys, flags: u8, paused: bool) -> Instruction {
+    build(keys.program_id, "set_pause_flags", (flags, paused), pool_metadata_accounts(keys))
+}
+
+pub fn update_stake_limits(keys: &PoolKeys, max_stake_per_wallet: u64, max_total_staked: u64) -> Instruction {
+    keys.admin("update_stake_limits", (max_stake_per_wallet, max_total_staked))
+}
+
+pub fn set_allowlist_root(keys: &PoolKeys, root: [u8; 32]) -> Instruction {
+    keys.admin("set_allowlist_root", root)
+}
+
+pub fn set_restake_pool(keys: &PoolKeys, restake_pool: Pubkey) -> Instruction {
+    keys.admin("set_restake_pool", restake_pool)
+}
+
+pub fn update_commission_vesting(keys: &PoolKeys, cliff: i64, duration: i64) -> Instruction {
+    keys.admin("update_commission_vesting", (cliff, duration))
+}
+
+pub fn update_referral_guards(keys: &PoolKeys, min_referee_stake: u64, min_referrer_stake_age: i64) -> Instruction {
+    keys.admin("update_referral_guards", (min_referee_stake, min_referrer_stake_age))
+}
+
+pub fn toggle_referral_system(keys: &PoolKeys, enabled: bool) -> Instruction {
+    keys.admin("toggle_referral_system", enabled)
+}
+
+pub fn set_strict_referrer_accounts(keys: &PoolKeys, enabled: bool) -> Instruction {
+    keys.admin("set_strict_referrer_accounts", enabled)
+}
+
+fn pool_metadata_accounts(keys: &PoolKeys) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(keys.pool, false),
+        AccountMeta::new(keys.pool_entry(), false),
+        AccountMeta::new_readonly(keys.authority, true),
+    ]
+}
+
+/// The entry's status isn't an argument: the program derives it from the pool.
+pub fn update_pool_metadata(keys: &PoolKeys, name: String, start_time: i64, end_time: i64) -> Instruction {
+    build(keys.program_id, "update_pool_metadata", (name, start_time, end_time), pool_metadata_accounts(keys))
+}
+
+pub fn sunset_pool(keys: &PoolKeys) -> Instruction {
+    build(keys.program_id, "sunset_pool", (), pool_metadata_accounts(keys))
+}
+
+pub fn close_pool(keys: &PoolKeys, authority_token_account: Pubkey, authority_reward_account: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "close_pool",
+        (),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(keys.pool_entry(), false),
+            AccountMeta::new(keys.staking_vault, false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(authority_token_account, false),
+            AccountMeta::new(authority_reward_account, false),
+            AccountMeta::new(keys.authority, true),
+            AccountMeta::new_readonly(keys.staking_token_program, false),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+pub fn withdraw_tokens(keys: &PoolKeys, admin_token_account: Pubkey, amount: u64) -> Instruction {
+    build(
+        keys.program_id,
+        "withdraw_tokens",
+        amount,
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new(keys.staking_vault, false),
+            AccountMeta::new(admin_token_account, false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(keys.authority, true),
+            AccountMeta::new_readonly(keys.staking_token_program, false),
+        ],
+    )
+}
+
+pub fn initialize_referrer_stats(keys: &PoolKeys, referrer: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "initialize_referrer_stats",
+        (),
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new(keys.referrer_stats(&referrer), false),
+            AccountMeta::new(referrer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn add_rewards(keys: &PoolKeys, funder: Pubkey, funder_reward_account: Pubkey, amount: u64) -> Instruction {
+    build(
+        keys.program_id,
+        "add_rewards",
+        amount,
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new(keys.reward_vault, false),
+            AccountMeta::new(funder_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+/// Ends the stake's referral link too: a later stake in the pool may link a different referrer.
+pub fn close_user_stake(program_id: Pubkey, user: Pubkey, pool: Pubkey) -> Instruction {
+    build(
+        program_id,
+        "close_user_stake",
+        (),
+        vec![AccountMeta::new(pda::user_stake(&program_id, &user, &pool).0, false), AccountMeta::new(user, true)],
+    )
+}
+
+pub fn close_referrer_stats(program_id: Pubkey, referrer: Pubkey, pool: Pubkey) -> Instruction {
+    build(
+        program_id,
+        "close_referrer_stats",
+        (),
+        vec![AccountMeta::new(pda::referrer_stats(&program_id, &pool, &referrer).0, false), AccountMeta::new(referrer, true)],
+    )
+}
+
+/// View instructions: simulate them and decode the return data (`u64`, `state::PoolState`,
+/// `i64`, `state::ReferrerSummary` respectively).
+pub fn get_pending_rewards(keys: &PoolKeys, user: Pubkey) -> Instruction {
+    build(keys.program_id, "get_pending_rewards", (), view_user_stake_accounts(keys, &user))
+}
+
+pub fn get_pool_state(keys: &PoolKeys) -> Instruction {
+    build(
+        keys.program_id,
+        "get_pool_state",
+        (),
+        vec![AccountMeta::new_readonly(keys.pool, false), AccountMeta::new_readonly(keys.reward_vault, false)],
+    )
+}
+
+pub fn get_unlock_time(keys: &PoolKeys, user: Pubkey) -> Instruction {
+    build(keys.program_id, "get_unlock_time", (), view_user_stake_accounts(keys, &user))
+}
+
+pub fn get_referrer_summary(program_id: Pubkey, referrer: Pubkey, pool: Pubkey) -> Instruction {
+    build(
+        program_id,
+        "get_referrer_summary",
+        (),
+        vec![AccountMeta::new_readonly(pda::referrer_stats(&program_id, &pool, &referrer).0, false)],
+    )
+}
+
+fn view_user_stake_accounts(keys: &PoolKeys, user: &Pubkey) -> Vec<AccountMeta> {
+    vec![AccountMeta::new_readonly(keys.pool, false), AccountMeta::new_readonly(keys.user_stake(user), false)]
+}
+
+/// Which `migrate_*` instruction to run on an old-layout account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrateKind {
+    /// A pool from before the registry; it is listed under `pool_id`, the registry's current `pool_count`.
+    StakingPool { pool_id: u64 },
+    /// A stake in `pool`, which has to be migrated first.
+    UserStake { pool: Pubkey },
+    /// `referrer`'s pre-per-pool stats, moved into their stats in the legacy `pool`; its `authority` signs.
+    ReferrerStats { pool: Pubkey, referrer: Pubkey, authority: Pubkey },
+}
+
+pub fn migrate(program_id: Pubkey, kind: MigrateKind, account: Pubkey, payer: Pubkey) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(account, false)];
+    let name = match kind {
+        MigrateKind::StakingPool { pool_id } => {
+            accounts.push(AccountMeta::new(pda::pool_registry(&program_id).0, false));
+            accounts.push(AccountMeta::new(pda::pool_entry(&program_id, pool_id).0, false));
+            "migrate_staking_pool"
+        }
+        MigrateKind::UserStake { pool } => {
+            accounts.push(AccountMeta::new(pool, false));
+            "migrate_user_stake"
+        }
+        MigrateKind::ReferrerStats { pool, referrer, authority } => {
+            accounts.push(AccountMeta::new(pool, false));
+            accounts.push(AccountMeta::new(pda::referrer_stats(&program_id, &pool, &referrer).0, false));
+            accounts.push(AccountMeta::new(referrer, false));
+            accounts.push(AccountMeta::new_readonly(authority, true));
+            "migrate_referrer_stats"
+        }
+    };
+    accounts.push(AccountMeta::new(payer, true));
+    accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+    build(program_id, name, (), accounts)
+}
+
+pub fn create_competition(keys: &PoolKeys, round_id: u64, start_time: i64, end_time: i64, prize_split_bps: Vec<u16>) -> Instruction {
+    let competition = pda::referral_competition(&keys.program_id, &keys.pool, round_id).0;
+    build(
+        keys.program_id,
+        "create_competition",
+        (round_id, start_time, end_time, prize_split_bps),
+        vec![
+            AccountMeta::new(keys.pool, false),
+            AccountMeta::new(competition, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(pda::associated_token_account(&competition, &keys.reward_mint, &keys.reward_token_program), false),
+            AccountMeta::new(keys.authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+            AccountMeta::new_readonly(crate::ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+    )
+}
+
+pub fn fund_competition(keys: &PoolKeys, competition: Pubkey, funder: Pubkey, funder_reward_account: Pubkey, amount: u64) -> Instruction {
+    build(
+        keys.program_id,
+        "fund_competition",
+        amount,
+        vec![
+            AccountMeta::new_readonly(competition, false),
+            AccountMeta::new(pda::associated_token_account(&competition, &keys.reward_mint, &keys.reward_token_program), false),
+            AccountMeta::new(funder_reward_account, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
+
+pub fn enter_competition(program_id: Pubkey, competition: Pubkey, referrer: Pubkey) -> Instruction {
+    build(
+        program_id,
+        "enter_competition",
+        (),
+        vec![
+            AccountMeta::new_readonly(competition, false),
+            AccountMeta::new(pda::competition_entry(&program_id, &competition, &referrer).0, false),
+            AccountMeta::new(referrer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// `winner_token_accounts` are the reward-mint token accounts of the leaderboard, in rank order.
+pub fn settle_competition(keys: &PoolKeys, competition: Pubkey, winner_token_accounts: &[Pubkey]) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(keys.pool, false),
+        AccountMeta::new(competition, false),
+        AccountMeta::new(pda::associated_token_account(&competition, &keys.reward_mint, &keys.reward_token_program), false),
+        AccountMeta::new_readonly(keys.reward_mint, false),
+        AccountMeta::new_readonly(keys.reward_token_program, false),
+    ];
+    accounts.extend(winner_token_accounts.iter().map(|key| AccountMeta::new(*key, false)));
+    build(keys.program_id, "settle_competition", (), accounts)
+}
+
+pub fn sweep_competition_remainder(keys: &PoolKeys, competition: Pubkey, destination: Pubkey) -> Instruction {
+    build(
+        keys.program_id,
+        "sweep_competition_remainder",
+        (),
+        vec![
+            AccountMeta::new_readonly(keys.pool, false),
+            AccountMeta::new_readonly(competition, false),
+            AccountMeta::new(pda::associated_token_account(&competition, &keys.reward_mint, &keys.reward_token_program), false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(keys.reward_mint, false),
+            AccountMeta::new_readonly(keys.authority, true),
+            AccountMeta::new_readonly(keys.reward_token_program, false),
+        ],
+    )
+}
@@ -0,0 +1,38 @@
+//! Client for the referral staking program (`Contract/StakingWithRefferal.rs`).
+//!
+//! - [`pda`]: derivation of every program address
+//! - [`instruction`]: typed instruction builders
+//! - [`upline`]: L1-L3 referrer account resolution from a `UserStake`
+//! - [`state`] / [`events`]: account and event decoding
+//! - [`math`]: the program's reward, lock and commission formulas (re-export of `stake-math`)
+//! - `rpc` (feature `rpc`): fetch helpers on top of a blocking `RpcClient`
+//!
+//! The layouts here mirror the program by hand; keep them in step when the program changes.
+
+use sha2::{Digest, Sha256};
+use solana_program::{pubkey, pubkey::Pubkey};
+
+pub mod error;
+pub mod events;
+pub mod instruction;
+pub mod pda;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod state;
+pub mod upline;
+
+pub use error::ClientError;
+pub use stake_math as math;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("5YNT6aKnnaDsUC7PYw3AJoe5GSjK9Gc76Ro79EMMWVSY");
+
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Anchor discriminator: first 8 bytes of `sha256("<namespace>:<name>")`
+/// (`global` for instructions, `account` for accounts, `event` for events).
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{namespace}:{name}").as_bytes());
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash[..8]);
+    disc
+}
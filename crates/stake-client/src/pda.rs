@@ -0,0 +1,74 @@
+//! Program-derived addresses, with the same seeds the program checks.
+
+use solana_program::{bpf_loader_upgradeable, pubkey::Pubkey};
+
+pub fn pool_registry(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_registry"], program_id)
+}
+
+/// The program's `ProgramData` account, which holds its upgrade authority.
+pub fn program_data(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+pub fn staking_pool(program_id: &Pubkey, token_mint: &Pubkey, pool_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"staking_pool", token_mint.as_ref(), &pool_id.to_le_bytes()], program_id)
+}
+
+/// Address of a pool created before pool ids; `migrate_staking_pool` keeps it there.
+pub fn legacy_staking_pool(program_id: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"staking_pool", token_mint.as_ref()], program_id)
+}
+
+pub fn pool_entry(program_id: &Pubkey, pool_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_entry", &pool_id.to_le_bytes()], program_id)
+}
+
+pub fn staking_vault(program_id: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"staking_vault", pool.as_ref()], program_id)
+}
+
+pub fn reward_vault(program_id: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"reward_vault", pool.as_ref()], program_id)
+}
+
+pub fn user_stake(program_id: &Pubkey, user: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user_stake", user.as_ref(), pool.as_ref()], program_id)
+}
+
+/// Commission is tracked per pool, so a referrer has one of these in every pool they refer into.
+pub fn referrer_stats(program_id: &Pubkey, pool: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer_stats", pool.as_ref(), referrer.as_ref()], program_id)
+}
+
+/// Address of stats created before per-pool commission; `migrate_referrer_stats` moves them into a pool's.
+pub fn legacy_referrer_stats(program_id: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer_stats", referrer.as_ref()], program_id)
+}
+
+/// `index` counts the referrer's grants in `pool` (`ReferrerStats::grant_count`).
+pub fn commission_grant(program_id: &Pubkey, pool: &Pubkey, referrer: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"commission_grant", pool.as_ref(), referrer.as_ref(), &index.to_le_bytes()], program_id)
+}
+
+pub fn referral_competition(program_id: &Pubkey, pool: &Pubkey, round_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referral_competition", pool.as_ref(), &round_id.to_le_bytes()], program_id)
+}
+
+pub fn competition_entry(program_id: &Pubkey, competition: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"competition_entry", competition.as_ref(), referrer.as_ref()], program_id)
+}
+
+/// Temporary wSOL account native pools unwrap through on unstake.
+pub fn native_unwrap(program_id: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"native_unwrap", user.as_ref()], program_id)
+}
+
+/// Associated token account of `owner` for `mint` under `token_program` (SPL Token or Token-2022).
+pub fn associated_token_account(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &crate::ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
@@ -0,0 +1,85 @@
+//! Fetch helpers on top of a blocking `RpcClient`.
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    instruction::PoolKeys,
+    pda,
+    state::{ProgramAccount, ReferrerStats, StakingPool, UserStake},
+    upline::{LinkAccounts, Upline},
+    ClientError,
+};
+
+/// Fetches and decodes `address`; `Ok(None)` if it doesn't exist.
+pub fn fetch_optional<T: ProgramAccount>(client: &RpcClient, address: &Pubkey) -> Result<Option<T>, ClientError> {
+    let account = client.get_account_with_commitment(address, client.commitment())?.value;
+    account.map(|account| T::try_from_account_data(&account.data)).transpose()
+}
+
+pub fn fetch<T: ProgramAccount>(client: &RpcClient, address: &Pubkey) -> Result<T, ClientError> {
+    fetch_optional(client, address)?.ok_or(ClientError::AccountNotFound(*address))
+}
+
+/// Reads the pool and the token programs of its staking and reward mints (their owners).
+pub fn fetch_pool_keys(client: &RpcClient, program_id: &Pubkey, pool: &Pubkey) -> Result<(PoolKeys, StakingPool), ClientError> {
+    let state: StakingPool = fetch(client, pool)?;
+    let owner = |mint: &Pubkey| -> Result<Pubkey, ClientError> {
+        let account = client.get_account_with_commitment(mint, client.commitment())?.value;
+        account.map(|account| account.owner).ok_or(ClientError::AccountNotFound(*mint))
+    };
+    let staking_token_program = owner(&state.token_mint)?;
+    let reward_token_program = owner(&state.reward_mint)?;
+    Ok((PoolKeys::from_state(*program_id, *pool, &state, staking_token_program, reward_token_program), state))
+}
+
+/// Upline of `user`'s stake in `pool`, skipping levels whose `ReferrerStats` was never created.
+pub fn resolve_upline(client: &RpcClient, program_id: &Pubkey, user: &Pubkey, pool: &Pubkey) -> Result<Upline, ClientError> {
+    let address = pda::user_stake(program_id, user, pool).0;
+    let user_stake: UserStake = fetch(client, &address)?;
+    let upline = Upline::from_user_stake(program_id, &user_stake);
+    let [l1, l2, l3] = existing(client, [upline.l1, upline.l2, upline.l3])?;
+    Ok(Upline { l1, l2, l3 })
+}
+
+/// Accounts for `stake_with_referral`: the current upline if `user` is already linked in `pool`,
+/// otherwise the chain through `referrer` (if any).
+pub fn resolve_link(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    user: &Pubkey,
+    pool: &Pubkey,
+    referrer: Option<&Pubkey>,
+) -> Result<LinkAccounts, ClientError> {
+    let own: Option<UserStake> = fetch_optional(client, &pda::user_stake(program_id, user, pool).0)?;
+    if let Some(own) = own.filter(|stake| stake.referrer.is_some()) {
+        let upline = Upline::from_user_stake(program_id, &own);
+        let [l1, l2, l3] = existing(client, [upline.l1, upline.l2, upline.l3])?;
+        return Ok(LinkAccounts::existing(&Upline { l1, l2, l3 }));
+    }
+    let Some(referrer) = referrer else {
+        return Ok(LinkAccounts::default());
+    };
+
+    let referrer_stake: UserStake = fetch(client, &pda::user_stake(program_id, referrer, pool).0)?;
+    let l2_stake: Option<UserStake> = match referrer_stake.referrer {
+        Some(l2) => fetch_optional(client, &pda::user_stake(program_id, &l2, pool).0)?,
+        None => None,
+    };
+    let mut link = LinkAccounts::resolve(program_id, pool, referrer, &referrer_stake, l2_stake.as_ref());
+    // The referrer's own stats must exist for the link; only the levels above it are optional
+    fetch::<ReferrerStats>(client, &pda::referrer_stats(program_id, pool, referrer).0)?;
+    [link.referrer_stats_l2, link.referrer_stats_l3] = existing(client, [link.referrer_stats_l2, link.referrer_stats_l3])?;
+    Ok(link)
+}
+
+/// Keeps only the addresses that exist on chain.
+fn existing<const N: usize>(client: &RpcClient, keys: [Option<Pubkey>; N]) -> Result<[Option<Pubkey>; N], ClientError> {
+    let lookup: Vec<Pubkey> = keys.iter().flatten().copied().collect();
+    if lookup.is_empty() {
+        return Ok(keys);
+    }
+    let accounts = client.get_multiple_accounts(&lookup)?;
+    let found: Vec<Pubkey> = lookup.iter().zip(accounts).filter(|(_, account)| account.is_some()).map(|(key, _)| *key).collect();
+    Ok(keys.map(|key| key.filter(|key| found.contains(key))))
+}
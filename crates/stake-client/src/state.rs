@@ -0,0 +1,280 @@
+//! Mirrors of the program's accounts and view return types.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::{discriminator, math, ClientError};
+
+pub const PAUSE_STAKE: u8 = 1 << 0;
+pub const PAUSE_UNSTAKE: u8 = 1 << 1;
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+pub const PAUSE_REFERRAL_CLAIM: u8 = 1 << 3;
+pub const PAUSE_REFERRAL_LINK: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_STAKE | PAUSE_UNSTAKE | PAUSE_CLAIM | PAUSE_REFERRAL_CLAIM | PAUSE_REFERRAL_LINK;
+
+/// An Anchor account of this program: checks the discriminator and decodes the body.
+pub trait ProgramAccount: BorshDeserialize {
+    const NAME: &'static str;
+
+    fn discriminator() -> [u8; 8] {
+        discriminator("account", Self::NAME)
+    }
+
+    /// Trailing bytes (reserved space, unused string capacity) are ignored.
+    fn try_from_account_data(data: &[u8]) -> Result<Self, ClientError> {
+        if data.len() < 8 || data[..8] != Self::discriminator() {
+            return Err(ClientError::DiscriminatorMismatch(Self::NAME));
+        }
+        let mut body = &data[8..];
+        Self::deserialize(&mut body).map_err(|err| ClientError::Deserialize(Self::NAME, err))
+    }
+}
+
+macro_rules! program_account {
+    ($($name:ident),* $(,)?) => {
+        $(impl ProgramAccount for $name {
+            const NAME: &'static str = stringify!($name);
+        })*
+    };
+}
+
+program_account!(
+    StakingPool,
+    UserStake,
+    ReferrerStats,
+    PoolRegistry,
+    PoolRegistryEntry,
+    CommissionGrant,
+    ReferralCompetition,
+    CompetitionEntry,
+);
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct StakingPool {
+    pub pool_id: u64,
+    pub authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub fee_receiver: Pubkey,
+    pub reward_per_token_stored: u128,
+    pub reward_rate: u64,
+    pub total_staked: u64,
+    pub fee_stake: u64,
+    pub fee_unstake: u64,
+    pub fee_claim: u64,
+    pub min_stake_amount: u64,
+    pub max_stake_per_wallet: u64,
+    pub max_total_staked: u64,
+    pub allowlist_root: [u8; 32],
+    pub last_update_time: i64,
+    pub lock_duration: i64,
+    pub min_referee_stake: u64,
+    pub min_referrer_stake_age: i64,
+    pub commission_vesting_cliff: i64,
+    pub commission_vesting_duration: i64,
+    pub restake_pool: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reward_liability: u64,
+    pub sunset: bool,
+    pub emergency_mode: bool,
+    pub referral_l1_bps: u16,
+    pub referral_l2_bps: u16,
+    pub referral_l3_bps: u16,
+    pub referral_enabled: bool,
+    pub strict_referrer_accounts: bool,
+    pub pause_flags: u8,
+    pub bump: u8,
+    pub version: u8,
+    pub legacy_seeds: bool,
+    pub active_competition: Pubkey,
+    pub reserved: [u8; 31],
+}
+
+impl StakingPool {
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    pub fn has_allowlist(&self) -> bool {
+        self.allowlist_root != [0u8; 32]
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct UserStake {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub staked_amount: u64,
+    pub reward_per_token_paid: u128,
+    pub reward_pending: u64,
+    pub last_stake_time: i64,
+    pub referrer: Option<Pubkey>,
+    pub referrer_l2: Option<Pubkey>,
+    pub referrer_l3: Option<Pubkey>,
+    pub total_earned: u64,
+    pub first_stake_time: i64,
+    pub allowlist_root: [u8; 32],
+    pub allowlist_cap: u64,
+    pub frozen: bool,
+    pub unstake_frozen: bool,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+impl UserStake {
+    /// Claimable staking reward at `now`, as `get_pending_rewards` computes it.
+    pub fn pending_rewards(&self, pool: &StakingPool, now: i64) -> u64 {
+        if self.staked_amount == 0 {
+            return self.reward_pending;
+        }
+        let accumulator = math::Accumulator {
+            reward_per_token_stored: pool.reward_per_token_stored,
+            last_update_time: pool.last_update_time,
+        }
+        .update(pool.reward_rate, pool.total_staked, pool.end_time, now);
+        let earned = math::pending_reward(self.staked_amount, accumulator.reward_per_token_stored, self.reward_per_token_paid);
+        self.reward_pending.saturating_add(earned)
+    }
+
+    /// Earliest time `unstake` accepts this stake (ignoring the sunset waiver), as `get_unlock_time` computes it.
+    pub fn unlock_time(&self, pool: &StakingPool) -> Option<i64> {
+        math::unlock_time(self.last_stake_time, pool.lock_duration)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ReferrerStats {
+    pub referrer: Pubkey,
+    pub total_referrals: u64,
+    pub total_commission_earned: u64,
+    pub pending_rewards: u64,
+    pub volume_referred: u64,
+    pub active_stake_l1: u64,
+    pub active_stake_l2: u64,
+    pub active_stake_l3: u64,
+    pub grant_count: u64,
+    pub flagged: bool,
+    pub frozen: bool,
+    pub version: u8,
+    pub pool: Pubkey,
+    pub open_grants: u64,
+    pub reserved: [u8; 24],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct PoolRegistry {
+    pub authority: Pubkey,
+    pub pool_count: u64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolStatus {
+    Active,
+    Paused,
+    Ended,
+    Sunset,
+    Closed,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct PoolRegistryEntry {
+    pub pool: Pubkey,
+    pub pool_id: u64,
+    pub token_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub name: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub status: PoolStatus,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct CommissionGrant {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub index: u64,
+    pub total_amount: u64,
+    pub released_amount: u64,
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub referrer: Pubkey,
+    pub volume: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ReferralCompetition {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub prize_vault: Pubkey,
+    pub prize_split_bps: Vec<u16>,
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub total_volume: u64,
+    pub settled: bool,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct CompetitionEntry {
+    pub competition: Pubkey,
+    pub referrer: Pubkey,
+    pub volume: u64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 32],
+}
+
+/// Return data of `get_pool_state`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct PoolState {
+    pub total_staked: u64,
+    pub reward_rate: u64,
+    pub reward_per_token_stored: u128,
+    pub reward_vault_balance: u64,
+    pub reward_liability: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub lock_duration: i64,
+    pub pause_flags: u8,
+    pub sunset: bool,
+    pub emergency_mode: bool,
+    pub accepting_stakes: bool,
+    pub timestamp: i64,
+}
+
+/// Return data of `get_referrer_summary`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ReferrerSummary {
+    pub referrer: Pubkey,
+    pub pool: Pubkey,
+    pub total_referrals: u64,
+    pub pending_rewards: u64,
+    pub total_commission_earned: u64,
+    pub volume_referred: u64,
+    pub active_stake_l1: u64,
+    pub active_stake_l2: u64,
+    pub active_stake_l3: u64,
+    pub grant_count: u64,
+    pub flagged: bool,
+    pub frozen: bool,
+}
@@ -0,0 +1,80 @@
+//! L1-L3 referrer account resolution.
+//!
+//! The program only updates a level's `ReferrerStats` when that account is passed, and in strict
+//! mode rejects stakes and claims if one is missing (`unstake` and `emergency_withdraw` skip it
+//! instead), so callers should always pass the full upline.
+//! Note that a `ReferrerStats` only exists once the referrer has called `initialize_referrer_stats`
+//! for that pool;
+//! the `rpc` helpers drop levels whose account is missing, except L1's on a new link, which the
+//! program requires.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::{pda, state::UserStake};
+
+/// `ReferrerStats` addresses of a linked stake's upline, as passed to `claim_rewards`,
+/// `claim_all`, `unstake` and `emergency_withdraw`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Upline {
+    pub l1: Option<Pubkey>,
+    pub l2: Option<Pubkey>,
+    pub l3: Option<Pubkey>,
+}
+
+impl Upline {
+    pub fn from_user_stake(program_id: &Pubkey, user_stake: &UserStake) -> Self {
+        let stats = |referrer: Option<Pubkey>| referrer.map(|key| pda::referrer_stats(program_id, &user_stake.pool, &key).0);
+        Self {
+            l1: stats(user_stake.referrer),
+            l2: stats(user_stake.referrer_l2),
+            l3: stats(user_stake.referrer_l3),
+        }
+    }
+}
+
+/// Referral accounts of `stake_with_referral`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkAccounts {
+    pub referrer_stats: Option<Pubkey>,
+    pub referrer_user_stake: Option<Pubkey>,
+    pub referrer_stats_l2: Option<Pubkey>,
+    pub l2_user_stake: Option<Pubkey>,
+    pub referrer_stats_l3: Option<Pubkey>,
+}
+
+impl LinkAccounts {
+    /// A stake that is already linked: only the upline's stats are touched (active stake).
+    pub fn existing(upline: &Upline) -> Self {
+        Self {
+            referrer_stats: upline.l1,
+            referrer_stats_l2: upline.l2,
+            referrer_stats_l3: upline.l3,
+            ..Self::default()
+        }
+    }
+
+    /// A first stake linking to `referrer`. `referrer_stake` is the referrer's `UserStake` in this
+    /// pool, which the program requires; `l2_stake` is its own referrer's, `None` if it doesn't exist.
+    pub fn resolve(
+        program_id: &Pubkey,
+        pool: &Pubkey,
+        referrer: &Pubkey,
+        referrer_stake: &UserStake,
+        l2_stake: Option<&UserStake>,
+    ) -> Self {
+        let mut accounts = Self {
+            referrer_stats: Some(pda::referrer_stats(program_id, pool, referrer).0),
+            referrer_user_stake: Some(pda::user_stake(program_id, referrer, pool).0),
+            ..Self::default()
+        };
+        let Some(l2) = referrer_stake.referrer else {
+            return accounts;
+        };
+        accounts.referrer_stats_l2 = Some(pda::referrer_stats(program_id, pool, &l2).0);
+        if let Some(l2_stake) = l2_stake {
+            accounts.l2_user_stake = Some(pda::user_stake(program_id, &l2, pool).0);
+            accounts.referrer_stats_l3 = l2_stake.referrer.map(|l3| pda::referrer_stats(program_id, pool, &l3).0);
+        }
+        accounts
+    }
+}
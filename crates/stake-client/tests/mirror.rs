@@ -0,0 +1,229 @@
+//! Checks the hand-written mirrors against `Contract/StakingWithRefferal.rs`: account sizes,
+//! instruction discriminators and event discriminators.
+
+use borsh::BorshSerialize;
+use sha2::{Digest, Sha256};
+use solana_program::pubkey::Pubkey;
+use stake_client::{
+    events::StakeEvent,
+    instruction::{self, AllowlistProof, MigrateKind, PoolKeys, RestakeTarget},
+    state::{
+        CommissionGrant, CompetitionEntry, LeaderboardEntry, PoolRegistry, PoolRegistryEntry, PoolStatus, ProgramAccount,
+        ReferralCompetition, ReferrerStats, StakingPool, UserStake,
+    },
+    upline::{LinkAccounts, Upline},
+};
+
+const PROGRAM: &str = include_str!("../../../Contract/StakingWithRefferal.rs");
+
+fn sha256_prefix(preimage: &str) -> [u8; 8] {
+    let hash = Sha256::digest(preimage.as_bytes());
+    hash[..8].try_into().unwrap()
+}
+
+/// Lines of `<header> {` ... the matching top-level `}` in the program source.
+fn block(header: &str) -> Vec<&'static str> {
+    let start = format!("{header} {{");
+    PROGRAM
+        .lines()
+        .skip_while(|line| *line != start)
+        .skip(1)
+        .take_while(|line| *line != "}")
+        .collect()
+}
+
+/// Borsh size of a field type at capacity, as `#[derive(InitSpace)]` counts it; `max_len` bounds a `String` or `Vec`.
+fn type_space(ty: &str, max_len: Option<usize>) -> usize {
+    match ty {
+        "Pubkey" => 32,
+        "u128" | "i128" => 16,
+        "u64" | "i64" => 8,
+        "u32" | "i32" => 4,
+        "u16" | "i16" => 2,
+        "u8" | "i8" | "bool" => 1,
+        "String" => 4 + max_len.expect("String without max_len"),
+        _ if ty.starts_with("Vec<") => 4 + max_len.expect("Vec without max_len") * type_space(&ty[4..ty.len() - 1], None),
+        _ if ty.starts_with("Option<") => 1 + type_space(&ty[7..ty.len() - 1], None),
+        _ if ty.starts_with("[u8; ") => ty[5..ty.len() - 1].parse().unwrap(),
+        // Fieldless enums take their one-byte tag
+        _ if PROGRAM.contains(&format!("pub enum {ty} {{")) => 1,
+        _ => struct_space(ty),
+    }
+}
+
+/// Sum of the field sizes of the program's struct `name`.
+fn struct_space(name: &str) -> usize {
+    let mut max_len = None;
+    let mut space = 0;
+    for line in block(&format!("pub struct {name}")) {
+        let line = line.trim();
+        if let Some(attr) = line.strip_prefix("#[max_len(") {
+            max_len = Some(attr.split(')').next().unwrap().parse().unwrap());
+        } else if let Some(field) = line.split("//").next().unwrap().trim().strip_prefix("pub ") {
+            space += type_space(field.split_once(": ").unwrap().1.trim_end_matches(','), max_len.take());
+        }
+    }
+    assert!(space > 0, "no struct `{name}`");
+    space
+}
+
+/// The program's allocated space for `name`: its literal `SIZE`, or the sum of its fields where it derives `InitSpace`.
+fn program_space(name: &str) -> usize {
+    let size = block(&format!("impl {name}"))
+        .into_iter()
+        .find_map(|line| line.trim().strip_prefix("pub const SIZE: usize = "))
+        .map(|size| size.trim_end_matches(';'));
+    match size {
+        Some(size) if size != "Self::INIT_SPACE" => size.parse().unwrap(),
+        _ => struct_space(name),
+    }
+}
+
+/// Decodes a body of `0x01` bytes (every `Option` is `Some`) and checks it re-encodes to all of it.
+fn assert_fixed_layout<T: ProgramAccount + BorshSerialize>() {
+    let space = program_space(T::NAME);
+    let mut data = T::discriminator().to_vec();
+    data.resize(8 + space, 1);
+    let account = T::try_from_account_data(&data).unwrap();
+    assert_eq!(8 + account.try_to_vec().unwrap().len(), 8 + space, "{}", T::NAME);
+}
+
+#[test]
+fn fixed_account_sizes_match_program() {
+    assert_fixed_layout::<StakingPool>();
+    assert_fixed_layout::<UserStake>();
+    assert_fixed_layout::<ReferrerStats>();
+    assert_fixed_layout::<PoolRegistry>();
+    assert_fixed_layout::<CommissionGrant>();
+    assert_fixed_layout::<CompetitionEntry>();
+}
+
+#[test]
+fn variable_account_sizes_match_program_at_capacity() {
+    let entry = PoolRegistryEntry {
+        pool: Pubkey::new_unique(),
+        pool_id: u64::MAX,
+        token_mint: Pubkey::new_unique(),
+        reward_mint: Pubkey::new_unique(),
+        name: "x".repeat(32),
+        start_time: 0,
+        end_time: 0,
+        status: PoolStatus::Closed,
+        bump: 255,
+        version: 1,
+        reserved: [0u8; 32],
+    };
+    assert_eq!(8 + entry.try_to_vec().unwrap().len(), 8 + program_space("PoolRegistryEntry"));
+
+    let competition = ReferralCompetition {
+        pool: Pubkey::new_unique(),
+        round_id: 0,
+        start_time: 0,
+        end_time: 0,
+        prize_vault: Pubkey::new_unique(),
+        prize_split_bps: vec![1_000; 10],
+        leaderboard: vec![LeaderboardEntry { referrer: Pubkey::new_unique(), volume: 1 }; 10],
+        total_volume: 0,
+        settled: false,
+        bump: 255,
+        version: 1,
+        reserved: [0u8; 32],
+    };
+    assert_eq!(8 + competition.try_to_vec().unwrap().len(), 8 + program_space("ReferralCompetition"));
+}
+
+/// One instruction from every builder (each `MigrateKind` counts as its own instruction).
+fn every_instruction() -> Vec<solana_program::instruction::Instruction> {
+    let program_id = stake_client::PROGRAM_ID;
+    let key = Pubkey::new_unique();
+    let keys = PoolKeys::derive(program_id, key, key, 0, key, key, key);
+    let upline = Upline { l1: Some(key), l2: Some(key), l3: Some(key) };
+    let link = LinkAccounts::existing(&upline);
+    let proof = AllowlistProof { cap: 1, proof: vec![[0u8; 32]] };
+    let other = RestakeTarget::OtherPool { pool: key, staking_vault: key, upline };
+    vec![
+        instruction::initialize_registry(program_id, key),
+        instruction::initialize(&keys, 1, true, "pool".into(), 0, 0),
+        instruction::stake_with_referral(&keys, key, None, 1, Some(key), Some(proof), &link, Some((key, key))),
+        instruction::claim_rewards(&keys, key, key, &upline, Some(other)),
+        instruction::claim_all(&keys, key, key, &upline, true),
+        instruction::claim_referral_rewards(&keys, key, key),
+        instruction::vest_referral_rewards(&keys, key, 0),
+        instruction::release_vested_commission(&keys, key, key, key),
+        instruction::flag_referrer(&keys, key, true),
+        instruction::set_referrer_frozen(&keys, key, true, 0),
+        instruction::set_user_stake_frozen(&keys, key, true, true, 0),
+        instruction::clawback_commission_grant(&keys, key, key),
+        instruction::unstake(&keys, key, None, &upline, 1),
+        instruction::emergency_withdraw(&keys, key, None, &upline),
+        instruction::set_emergency_mode(&keys, true),
+        instruction::update_fees(&keys, 0, 0, 0, key),
+        instruction::update_referral_rates(&keys, 0, 0, 0),
+        instruction::update_lock_duration(&keys, 0),
+        instruction::update_reward_rate(&keys, 0),
+        instruction::set_pause_state(&keys, true),
+        instruction::set_pause_flags(&keys, 1, true),
+        instruction::update_stake_limits(&keys, 0, 0),
+        instruction::set_allowlist_root(&keys, [0u8; 32]),
+        instruction::set_restake_pool(&keys, key),
+        instruction::update_commission_vesting(&keys, 0, 0),
+        instruction::update_referral_guards(&keys, 0, 0),
+        instruction::toggle_referral_system(&keys, true),
+        instruction::set_strict_referrer_accounts(&keys, true),
+        instruction::update_pool_metadata(&keys, "pool".into(), 0, 0),
+        instruction::sunset_pool(&keys),
+        instruction::close_pool(&keys, key, key),
+        instruction::withdraw_tokens(&keys, key, 1),
+        instruction::initialize_referrer_stats(&keys, key),
+        instruction::add_rewards(&keys, key, key, 1),
+        instruction::close_user_stake(program_id, key, key),
+        instruction::close_referrer_stats(program_id, key, key),
+        instruction::get_pending_rewards(&keys, key),
+        instruction::get_pool_state(&keys),
+        instruction::get_unlock_time(&keys, key),
+        instruction::get_referrer_summary(program_id, key, key),
+        instruction::migrate(program_id, MigrateKind::StakingPool { pool_id: 0 }, key, key),
+        instruction::migrate(program_id, MigrateKind::UserStake { pool: key }, key, key),
+        instruction::migrate(program_id, MigrateKind::ReferrerStats { pool: key, referrer: key, authority: key }, key, key),
+        instruction::create_competition(&keys, 0, 0, 1, vec![10_000]),
+        instruction::fund_competition(&keys, key, key, key, 1),
+        instruction::enter_competition(program_id, key, key),
+        instruction::settle_competition(&keys, key, &[key]),
+        instruction::sweep_competition_remainder(&keys, key, key),
+    ]
+}
+
+#[test]
+fn instruction_discriminators_cover_every_program_instruction() {
+    let names: Vec<&str> = block("pub mod solana_staking_referral")
+        .into_iter()
+        .filter_map(|line| line.strip_prefix("    pub fn "))
+        .map(|rest| rest.split(['(', '<']).next().unwrap())
+        .collect();
+    let built: Vec<[u8; 8]> = every_instruction().iter().map(|ix| ix.data[..8].try_into().unwrap()).collect();
+
+    assert_eq!(built.len(), names.len());
+    for name in names {
+        let expected = sha256_prefix(&format!("global:{name}"));
+        assert!(built.contains(&expected), "no builder for `{name}`");
+    }
+}
+
+#[test]
+fn event_discriminators_decode_every_program_event() {
+    let names: Vec<&str> = PROGRAM
+        .lines()
+        .zip(PROGRAM.lines().skip(1))
+        .filter(|(attr, _)| *attr == "#[event]")
+        .map(|(_, decl)| decl.strip_prefix("pub struct ").unwrap().trim_end_matches(" {"))
+        .collect();
+    assert!(!names.is_empty());
+
+    for name in names {
+        // An all-zero body is a valid borsh encoding of any event (`None`, `false`, empty vecs).
+        let mut payload = sha256_prefix(&format!("event:{name}")).to_vec();
+        payload.resize(8 + 1024, 0);
+        let event = StakeEvent::decode(&payload).unwrap_or_else(|| panic!("`{name}` doesn't decode"));
+        assert_eq!(event.name(), name);
+    }
+}
@@ -0,0 +1,36 @@
+//! The indexer behind the `stake-indexer` binary: transaction sources, the SQLite store, and the
+//! loops that feed one into the other.
+
+pub mod source;
+pub mod store;
+
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use stake_client::events::parse_logs;
+
+use crate::{source::TxRecord, store::Store};
+
+/// Decodes and stores `tx`'s events; `checkpoint` is the source whose checkpoint moves to `tx`.
+pub fn index(store: &mut Store, program_id: &Pubkey, tx: &TxRecord, checkpoint: Option<&str>) -> Result<usize> {
+    let events = parse_logs(program_id, &tx.logs);
+    store.record(tx, &events, checkpoint)
+}
+
+/// Fetches and indexes `signatures` in order under `source`'s checkpoint.
+///
+/// Each transaction moves the checkpoint to itself as it's stored, so a failed fetch leaves it at
+/// the last one actually indexed and the next run retries from there.
+pub fn index_signatures(
+    store: &mut Store,
+    program_id: &Pubkey,
+    source: &str,
+    signatures: &[String],
+    mut fetch: impl FnMut(&str) -> Result<TxRecord>,
+) -> Result<usize> {
+    let mut events = 0;
+    for signature in signatures {
+        let tx = fetch(signature)?;
+        events += index(store, program_id, &tx, Some(source))?;
+    }
+    Ok(events)
+}
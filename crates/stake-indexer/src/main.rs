@@ -0,0 +1,69 @@
+//! `stake-indexer`: decodes the staking program's events from transaction logs into SQLite.
+//!
+//! `rpc` walks the program's signature history and resumes from the last indexed signature;
+//! `files` imports saved `getTransaction` JSON. Both are idempotent: a transaction already in the
+//! database is skipped, so overlapping runs and re-imports don't duplicate rows.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use stake_indexer::{index, index_signatures, source, store::Store};
+
+#[derive(Parser)]
+#[command(name = "stake-indexer", version, about = "Index referral staking events into SQLite")]
+struct Cli {
+    /// SQLite database (created if missing)
+    #[arg(long, short = 'd', global = true, env = "STAKE_INDEX_DB", default_value = "stake-index.sqlite")]
+    db: PathBuf,
+    /// Staking program id
+    #[arg(long, global = true, env = "STAKE_PROGRAM_ID", default_value_t = stake_client::PROGRAM_ID)]
+    program_id: Pubkey,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index new transactions from an RPC endpoint (or a local validator)
+    Rpc {
+        #[arg(long, short = 'u', env = "STAKE_RPC_URL", default_value = "https://api.devnet.solana.com")]
+        url: String,
+        /// Stop after this many transactions; the next run continues from there
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Import saved `getTransaction` results (one transaction or an array per file)
+    Files { paths: Vec<PathBuf> },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut store = Store::open(&cli.db)?;
+
+    match &cli.command {
+        Command::Rpc { url, limit } => {
+            let client = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+            let source = format!("rpc:{}", cli.program_id);
+            let checkpoint = store.checkpoint(&source)?;
+            let signatures = source::signatures_since(&client, &cli.program_id, checkpoint.as_deref(), *limit)?;
+            println!("{} new transaction(s) since {}", signatures.len(), checkpoint.as_deref().unwrap_or("genesis"));
+            let events = index_signatures(&mut store, &cli.program_id, &source, &signatures, |signature| {
+                source::fetch_transaction(&client, signature)
+            })?;
+            println!("Indexed {events} event(s)");
+        }
+        Command::Files { paths } => {
+            let mut events = 0;
+            for path in paths {
+                for tx in source::read_file(path)? {
+                    events += index(&mut store, &cli.program_id, &tx, None)?;
+                }
+            }
+            println!("Indexed {events} event(s) from {} file(s)", paths.len());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+-- One row per transaction seen; events of failed transactions were rolled back and aren't indexed.
+CREATE TABLE IF NOT EXISTS transactions (
+    signature  TEXT PRIMARY KEY,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER,
+    failed     INTEGER NOT NULL
+);
+
+-- Every decoded event, including those without a dedicated table below.
+CREATE TABLE IF NOT EXISTS events (
+    signature   TEXT NOT NULL REFERENCES transactions(signature),
+    event_index INTEGER NOT NULL,
+    name        TEXT NOT NULL,
+    data        TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS pools (
+    pool        TEXT PRIMARY KEY,
+    pool_id     INTEGER NOT NULL,
+    authority   TEXT NOT NULL,
+    token_mint  TEXT NOT NULL,
+    reward_mint TEXT NOT NULL,
+    created_at  INTEGER NOT NULL,
+    sunset_at   INTEGER,
+    closed_at   INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS stakes (
+    signature    TEXT NOT NULL,
+    event_index  INTEGER NOT NULL,
+    pool         TEXT NOT NULL,
+    user         TEXT NOT NULL,
+    amount       INTEGER NOT NULL,
+    net_amount   INTEGER NOT NULL,
+    user_staked  INTEGER NOT NULL,
+    total_staked INTEGER NOT NULL,
+    timestamp    INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+-- kind: 'unstake' | 'emergency'
+CREATE TABLE IF NOT EXISTS unstakes (
+    signature         TEXT NOT NULL,
+    event_index       INTEGER NOT NULL,
+    kind              TEXT NOT NULL,
+    pool              TEXT NOT NULL,
+    user              TEXT NOT NULL,
+    amount            INTEGER NOT NULL,
+    net_amount        INTEGER NOT NULL,
+    forfeited_rewards INTEGER NOT NULL,
+    user_staked       INTEGER NOT NULL,
+    total_staked      INTEGER NOT NULL,
+    timestamp         INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+-- kind: 'reward' | 'referral' | 'all' | 'restake' | 'vested_commission'
+CREATE TABLE IF NOT EXISTS claims (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    kind        TEXT NOT NULL,
+    pool        TEXT NOT NULL,
+    claimant    TEXT NOT NULL,
+    amount      INTEGER NOT NULL,
+    net_amount  INTEGER NOT NULL,
+    timestamp   INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS referrals (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    pool        TEXT NOT NULL,
+    user        TEXT NOT NULL,
+    referrer    TEXT NOT NULL,
+    referrer_l2 TEXT,
+    referrer_l3 TEXT,
+    timestamp   INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+-- One row per credited level of a CommissionCredited event.
+CREATE TABLE IF NOT EXISTS commissions (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    level       INTEGER NOT NULL,
+    pool        TEXT NOT NULL,
+    referee     TEXT NOT NULL,
+    referrer    TEXT NOT NULL,
+    amount      INTEGER NOT NULL,
+    timestamp   INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index, level)
+);
+
+-- Vault movements outside user flows. kind: 'rewards_added' | 'tokens_withdrawn' | 'pool_closed_stake' | 'pool_closed_reward'
+CREATE TABLE IF NOT EXISTS funding (
+    signature     TEXT NOT NULL,
+    event_index   INTEGER NOT NULL,
+    kind          TEXT NOT NULL,
+    pool          TEXT NOT NULL,
+    counterparty  TEXT NOT NULL,
+    amount        INTEGER NOT NULL,
+    balance_after INTEGER,
+    timestamp     INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index, kind)
+);
+
+-- One row per changed parameter; values are rendered as text.
+-- target: the account changed when it isn't the pool itself (e.g. a flagged referrer).
+CREATE TABLE IF NOT EXISTS admin_changes (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    pool        TEXT NOT NULL,
+    target      TEXT,
+    event       TEXT NOT NULL,
+    field       TEXT NOT NULL,
+    old_value   TEXT,
+    new_value   TEXT NOT NULL,
+    timestamp   INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index, field)
+);
+
+-- Last transaction fully indexed per source, so `rpc` runs resume where they stopped.
+CREATE TABLE IF NOT EXISTS checkpoints (
+    source         TEXT PRIMARY KEY,
+    last_signature TEXT NOT NULL,
+    last_slot      INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS stakes_user ON stakes (user, pool);
+CREATE INDEX IF NOT EXISTS unstakes_user ON unstakes (user, pool);
+CREATE INDEX IF NOT EXISTS claims_claimant ON claims (claimant);
+CREATE INDEX IF NOT EXISTS commissions_referrer ON commissions (referrer);
+
+-- Pool TVL after every stake and withdrawal.
+CREATE VIEW IF NOT EXISTS tvl_history AS
+    SELECT pool, timestamp, total_staked FROM stakes
+    UNION ALL
+    SELECT pool, timestamp, total_staked FROM unstakes;
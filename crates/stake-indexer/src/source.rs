@@ -0,0 +1,100 @@
+//! Transaction sources. Both read the `getTransaction` JSON shape (`encoding: "json"`),
+//! so a saved RPC response and a live fetch go through the same parser.
+
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+/// The parts of a confirmed transaction the indexer needs.
+#[derive(Clone, Debug)]
+pub struct TxRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+impl TxRecord {
+    /// Accepts a `getTransaction` result or the full JSON-RPC response wrapping it.
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let value = value.get("result").unwrap_or(value);
+        let signature = value
+            .pointer("/transaction/signatures/0")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("transaction has no signature (was it fetched with encoding \"json\"?)"))?;
+        let meta = value.get("meta").ok_or_else(|| anyhow!("transaction {signature} has no meta"))?;
+        let logs = meta
+            .get("logMessages")
+            .and_then(Value::as_array)
+            .map(|lines| lines.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+            .unwrap_or_default();
+        Ok(Self {
+            signature: signature.to_owned(),
+            slot: value.get("slot").and_then(Value::as_u64).ok_or_else(|| anyhow!("transaction {signature} has no slot"))?,
+            block_time: value.get("blockTime").and_then(Value::as_i64),
+            failed: meta.get("err").is_some_and(|err| !err.is_null()),
+            logs,
+        })
+    }
+}
+
+/// Reads a saved file holding one transaction or an array of them.
+pub fn read_file(path: &Path) -> Result<Vec<TxRecord>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let value: Value = serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    let records = match value.as_array() {
+        Some(items) => items.iter().map(TxRecord::from_json).collect::<Result<Vec<_>>>(),
+        None => TxRecord::from_json(&value).map(|record| vec![record]),
+    };
+    records.with_context(|| format!("in {}", path.display()))
+}
+
+/// Signatures of `program_id` newer than `until`, oldest first, at most `max` of them.
+pub fn signatures_since(client: &RpcClient, program_id: &Pubkey, until: Option<&str>, max: Option<usize>) -> Result<Vec<String>> {
+    const PAGE: usize = 1_000;
+    let until = until.map(Signature::from_str).transpose().context("invalid checkpoint signature")?;
+    let mut before = None;
+    let mut signatures = Vec::new();
+    // The RPC pages newest first, so page all the way back to `until` even when capped: the cap has
+    // to drop the newest signatures, or the ones between `until` and the first page would be skipped
+    loop {
+        let page = client.get_signatures_for_address_with_config(
+            program_id,
+            GetConfirmedSignaturesForAddress2Config { before, until, limit: Some(PAGE), commitment: Some(client.commitment()) },
+        )?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let full = page.len() == PAGE;
+        signatures.extend(page.into_iter().map(|status| status.signature));
+        if !full {
+            break;
+        }
+    }
+    signatures.reverse();
+    if let Some(max) = max {
+        signatures.truncate(max);
+    }
+    Ok(signatures)
+}
+
+pub fn fetch_transaction(client: &RpcClient, signature: &str) -> Result<TxRecord> {
+    let params = json!([signature, {
+        "encoding": "json",
+        "maxSupportedTransactionVersion": 0,
+        "commitment": client.commitment().commitment,
+    }]);
+    let value: Value = client.send(RpcRequest::GetTransaction, params)?;
+    if value.is_null() {
+        return Err(anyhow!("transaction {signature} not found"));
+    }
+    TxRecord::from_json(&value)
+}
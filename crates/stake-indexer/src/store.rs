@@ -0,0 +1,264 @@
+//! SQLite storage. Rows are keyed by `(signature, event_index)`, and every insert is
+//! `INSERT OR IGNORE`, so reprocessing a transaction is a no-op.
+
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use solana_sdk::pubkey::Pubkey;
+use stake_client::events::StakeEvent;
+
+use crate::source::TxRecord;
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(include_str!("schema.sql"))?;
+        Ok(Self { conn })
+    }
+
+    pub fn checkpoint(&self, source: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT last_signature FROM checkpoints WHERE source = ?1", [source], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Writes `tx` and its events atomically, advancing `checkpoint` in the same transaction.
+    /// Returns the number of events indexed (0 if the transaction was already known).
+    pub fn record(&mut self, tx: &TxRecord, events: &[StakeEvent], checkpoint: Option<&str>) -> Result<usize> {
+        let db = self.conn.transaction()?;
+        let new = db.execute(
+            "INSERT OR IGNORE INTO transactions (signature, slot, block_time, failed) VALUES (?1, ?2, ?3, ?4)",
+            params![tx.signature, tx.slot, tx.block_time, tx.failed],
+        )? == 1;
+
+        let mut indexed = 0;
+        if new && !tx.failed {
+            for (index, event) in events.iter().enumerate() {
+                let previous = index.checked_sub(1).map(|previous| &events[previous]);
+                insert_event(&db, &tx.signature, index, event, previous)?;
+                indexed += 1;
+            }
+        }
+
+        if let Some(source) = checkpoint {
+            db.execute(
+                "INSERT INTO checkpoints (source, last_signature, last_slot) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (source) DO UPDATE SET last_signature = excluded.last_signature, last_slot = excluded.last_slot",
+                params![source, tx.signature, tx.slot],
+            )?;
+        }
+        db.commit()?;
+        Ok(indexed)
+    }
+}
+
+fn key(key: &Pubkey) -> String {
+    key.to_string()
+}
+
+fn opt_key(key: &Option<Pubkey>) -> Option<String> {
+    key.as_ref().map(Pubkey::to_string)
+}
+
+fn insert_event(db: &Transaction, sig: &str, index: usize, event: &StakeEvent, previous: Option<&StakeEvent>) -> Result<()> {
+    db.execute(
+        "INSERT OR IGNORE INTO events (signature, event_index, name, data) VALUES (?1, ?2, ?3, ?4)",
+        params![sig, index, event.name(), format!("{event:?}")],
+    )?;
+
+    let claim = |kind: &str, pool: &Pubkey, claimant: &Pubkey, amount: u64, net: u64, ts: i64| {
+        db.execute(
+            "INSERT OR IGNORE INTO claims (signature, event_index, kind, pool, claimant, amount, net_amount, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![sig, index, kind, key(pool), key(claimant), amount, net, ts],
+        )
+    };
+    let funding = |kind: &str, pool: &Pubkey, counterparty: &Pubkey, amount: u64, balance: Option<u64>, ts: i64| {
+        db.execute(
+            "INSERT OR IGNORE INTO funding (signature, event_index, kind, pool, counterparty, amount, balance_after, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![sig, index, kind, key(pool), key(counterparty), amount, balance, ts],
+        )
+    };
+    let admin = |pool: &Pubkey, target: Option<&Pubkey>, ts: i64, changes: &[(&str, Option<String>, String)]| -> rusqlite::Result<()> {
+        for (field, old, new) in changes {
+            db.execute(
+                "INSERT OR IGNORE INTO admin_changes (signature, event_index, pool, target, event, field, old_value, new_value, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![sig, index, key(pool), target.map(key), event.name(), field, old, new, ts],
+            )?;
+        }
+        Ok(())
+    };
+    fn change<T: ToString>(field: &str, old: T, new: T) -> (&str, Option<String>, String) {
+        (field, Some(old.to_string()), new.to_string())
+    }
+
+    match event {
+        StakeEvent::PoolInitialized(e) => {
+            db.execute(
+                "INSERT OR IGNORE INTO pools (pool, pool_id, authority, token_mint, reward_mint, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![key(&e.pool), e.pool_id, key(&e.authority), key(&e.token_mint), key(&e.reward_mint), e.timestamp],
+            )?;
+        }
+        StakeEvent::PoolSunsetStarted(e) => {
+            db.execute("UPDATE pools SET sunset_at = ?2 WHERE pool = ?1", params![key(&e.pool), e.timestamp])?;
+        }
+        StakeEvent::PoolClosed(e) => {
+            db.execute("UPDATE pools SET closed_at = ?2 WHERE pool = ?1", params![key(&e.pool), e.timestamp])?;
+            funding("pool_closed_stake", &e.pool, &e.authority, e.swept_stake_tokens, Some(0), e.timestamp)?;
+            funding("pool_closed_reward", &e.pool, &e.authority, e.swept_reward_tokens, Some(0), e.timestamp)?;
+        }
+        StakeEvent::Staked(e) => {
+            db.execute(
+                "INSERT OR IGNORE INTO stakes (signature, event_index, pool, user, amount, net_amount, user_staked, total_staked, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![sig, index, key(&e.pool), key(&e.user), e.amount, e.net_amount, e.user_staked, e.total_staked, e.timestamp],
+            )?;
+        }
+        StakeEvent::Unstaked(e) => {
+            db.execute(
+                "INSERT OR IGNORE INTO unstakes (signature, event_index, kind, pool, user, amount, net_amount, forfeited_rewards, user_staked, total_staked, timestamp)
+                 VALUES (?1, ?2, 'unstake', ?3, ?4, ?5, ?6, 0, ?7, ?8, ?9)",
+                params![sig, index, key(&e.pool), key(&e.user), e.amount, e.net_amount, e.user_staked, e.total_staked, e.timestamp],
+            )?;
+        }
+        StakeEvent::EmergencyWithdrawn(e) => {
+            db.execute(
+                "INSERT OR IGNORE INTO unstakes (signature, event_index, kind, pool, user, amount, net_amount, forfeited_rewards, user_staked, total_staked, timestamp)
+                 VALUES (?1, ?2, 'emergency', ?3, ?4, ?5, ?6, ?7, 0, ?8, ?9)",
+                params![sig, index, key(&e.pool), key(&e.user), e.amount, e.net_amount, e.forfeited_rewards, e.total_staked, e.timestamp],
+            )?;
+        }
+        StakeEvent::RewardsClaimed(e) => {
+            // A restake emits `RewardsRestaked` right before this, and that row already records the claim
+            let restaked = matches!(previous, Some(StakeEvent::RewardsRestaked(r)) if r.user == e.user && r.from_pool == e.pool);
+            if !restaked {
+                claim("reward", &e.pool, &e.user, e.amount, e.net_amount, e.timestamp)?;
+            }
+        }
+        StakeEvent::ReferralRewardsClaimed(e) => {
+            claim("referral", &e.pool, &e.referrer, e.amount, e.net_amount, e.timestamp)?;
+        }
+        StakeEvent::AllRewardsClaimed(e) => {
+            claim("all", &e.pool, &e.user, e.total, e.net_total, e.timestamp)?;
+        }
+        StakeEvent::RewardsRestaked(e) => {
+            claim("restake", &e.from_pool, &e.user, e.amount, e.net_amount, e.timestamp)?;
+        }
+        StakeEvent::CommissionReleased(e) => {
+            claim("vested_commission", &e.pool, &e.referrer, e.amount, e.amount, e.timestamp)?;
+        }
+        StakeEvent::NewReferral(e) => {
+            db.execute(
+                "INSERT OR IGNORE INTO referrals (signature, event_index, pool, user, referrer, referrer_l2, referrer_l3, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![sig, index, key(&e.pool), key(&e.user), key(&e.referrer), opt_key(&e.referrer_l2), opt_key(&e.referrer_l3), e.timestamp],
+            )?;
+        }
+        StakeEvent::CommissionCredited(e) => {
+            let levels = [(1, e.l1_referrer, e.l1_amount), (2, e.l2_referrer, e.l2_amount), (3, e.l3_referrer, e.l3_amount)];
+            for (level, referrer, amount) in levels {
+                let Some(referrer) = referrer.filter(|_| amount > 0) else {
+                    continue;
+                };
+                db.execute(
+                    "INSERT OR IGNORE INTO commissions (signature, event_index, level, pool, referee, referrer, amount, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![sig, index, level, key(&e.pool), key(&e.referee), key(&referrer), amount, e.timestamp],
+                )?;
+            }
+        }
+        StakeEvent::RewardsAdded(e) => {
+            funding("rewards_added", &e.pool, &e.funder, e.amount, Some(e.reward_vault_balance), e.timestamp)?;
+        }
+        StakeEvent::TokensWithdrawn(e) => {
+            funding("tokens_withdrawn", &e.pool, &e.destination, e.amount, Some(e.vault_balance), e.timestamp)?;
+        }
+        StakeEvent::FeesUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                change("fee_stake", e.old_fee_stake, e.new_fee_stake),
+                change("fee_unstake", e.old_fee_unstake, e.new_fee_unstake),
+                change("fee_claim", e.old_fee_claim, e.new_fee_claim),
+                change("fee_receiver", e.old_fee_receiver, e.new_fee_receiver),
+            ],
+        )?,
+        StakeEvent::ReferralRatesUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                change("referral_l1_bps", e.old_l1_bps, e.new_l1_bps),
+                change("referral_l2_bps", e.old_l2_bps, e.new_l2_bps),
+                change("referral_l3_bps", e.old_l3_bps, e.new_l3_bps),
+            ],
+        )?,
+        StakeEvent::LockDurationUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("lock_duration", e.old_duration, e.new_duration)])?,
+        StakeEvent::RewardRateUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("reward_rate", e.old_rate, e.new_rate)])?,
+        StakeEvent::EmergencyModeUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("emergency_mode", e.old_enabled, e.new_enabled)])?,
+        StakeEvent::PauseFlagsUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("pause_flags", e.old_flags, e.new_flags)])?,
+        StakeEvent::ReferralSystemToggled(e) => admin(&e.pool, None, e.timestamp, &[change("referral_enabled", e.old_enabled, e.new_enabled)])?,
+        StakeEvent::StrictReferrerAccountsUpdated(e) => {
+            admin(&e.pool, None, e.timestamp, &[change("strict_referrer_accounts", e.old_enabled, e.new_enabled)])?
+        }
+        StakeEvent::StakeLimitsUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                change("max_stake_per_wallet", e.old_max_stake_per_wallet, e.new_max_stake_per_wallet),
+                change("max_total_staked", e.old_max_total_staked, e.new_max_total_staked),
+            ],
+        )?,
+        StakeEvent::AllowlistRootUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("allowlist_root", hex(&e.old_root), hex(&e.new_root))])?,
+        StakeEvent::RestakePoolUpdated(e) => admin(&e.pool, None, e.timestamp, &[change("restake_pool", e.old_restake_pool, e.new_restake_pool)])?,
+        StakeEvent::CommissionVestingUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                change("commission_vesting_cliff", e.old_cliff, e.new_cliff),
+                change("commission_vesting_duration", e.old_duration, e.new_duration),
+            ],
+        )?,
+        StakeEvent::ReferralGuardsUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                change("min_referee_stake", e.old_min_referee_stake, e.new_min_referee_stake),
+                change("min_referrer_stake_age", e.old_min_referrer_stake_age, e.new_min_referrer_stake_age),
+            ],
+        )?,
+        StakeEvent::PoolMetadataUpdated(e) => admin(
+            &e.pool,
+            None,
+            e.timestamp,
+            &[
+                ("name", None, e.name.clone()),
+                change("start_time", e.old_start_time, e.new_start_time),
+                change("end_time", e.old_end_time, e.new_end_time),
+                change("status", format!("{:?}", e.old_status), format!("{:?}", e.new_status)),
+            ],
+        )?,
+        StakeEvent::ReferrerFlagUpdated(e) => admin(&e.pool, Some(&e.referrer), e.timestamp, &[change("flagged", e.old_flagged, e.new_flagged)])?,
+        // Kept in `events` only
+        _ => {}
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
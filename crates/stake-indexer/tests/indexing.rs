@@ -0,0 +1,139 @@
+//! Indexes the saved transactions in `tests/fixtures/history.json` (a pool, two stakes with a
+//! referral, a failed claim, a restaking claim and a wallet claim) and checks the rows it produces.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use rusqlite::Connection;
+use stake_client::PROGRAM_ID;
+use stake_indexer::{
+    index, index_signatures,
+    source::{self, TxRecord},
+    store::Store,
+};
+use tempfile::TempDir;
+
+const TABLES: [&str; 9] = ["transactions", "events", "pools", "stakes", "unstakes", "claims", "referrals", "commissions", "funding"];
+const SOURCE: &str = "rpc:test";
+
+fn history() -> Vec<TxRecord> {
+    source::read_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/history.json")).unwrap()
+}
+
+/// A fresh database and a second connection to read it back.
+fn open(dir: &TempDir) -> (Store, Connection) {
+    let path = dir.path().join("index.sqlite");
+    (Store::open(&path).unwrap(), Connection::open(&path).unwrap())
+}
+
+fn row_counts(db: &Connection) -> Vec<(&'static str, i64)> {
+    TABLES.iter().map(|table| (*table, db.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap())).collect()
+}
+
+/// Every event row as `signature:index:name`, in chain order.
+fn event_rows(db: &Connection) -> Vec<String> {
+    let mut query = db
+        .prepare("SELECT e.signature || ':' || e.event_index || ':' || e.name FROM events e JOIN transactions t USING (signature) ORDER BY t.slot, e.event_index")
+        .unwrap();
+    let rows = query.query_map([], |row| row.get(0)).unwrap();
+    rows.collect::<Result<_, _>>().unwrap()
+}
+
+fn import(store: &mut Store, txs: &[TxRecord]) -> usize {
+    txs.iter().map(|tx| index(store, &PROGRAM_ID, tx, None).unwrap()).sum()
+}
+
+#[test]
+fn importing_the_same_batch_twice_adds_nothing() {
+    let dir = TempDir::new().unwrap();
+    let (mut store, db) = open(&dir);
+    let txs = history();
+
+    assert_eq!(import(&mut store, &txs), 9);
+    let counts = row_counts(&db);
+    assert_eq!(
+        counts,
+        [("transactions", 6), ("events", 9), ("pools", 1), ("stakes", 2), ("unstakes", 0), ("claims", 2), ("referrals", 1), ("commissions", 2), ("funding", 0)]
+    );
+
+    assert_eq!(import(&mut store, &txs), 0);
+    assert_eq!(row_counts(&db), counts);
+}
+
+#[test]
+fn an_interrupted_run_resumes_from_its_checkpoint() {
+    let txs = history();
+    let signatures: Vec<String> = txs.iter().map(|tx| tx.signature.clone()).collect();
+    let fetch = |signature: &str| Ok(txs.iter().find(|tx| tx.signature == signature).unwrap().clone());
+
+    let straight_dir = TempDir::new().unwrap();
+    let (mut straight, straight_db) = open(&straight_dir);
+    index_signatures(&mut straight, &PROGRAM_ID, SOURCE, &signatures, fetch).unwrap();
+
+    // The fourth fetch drops, as an RPC timeout would
+    let dir = TempDir::new().unwrap();
+    let (mut store, db) = open(&dir);
+    let mut fetched = 0;
+    let result = index_signatures(&mut store, &PROGRAM_ID, SOURCE, &signatures, |signature| {
+        fetched += 1;
+        if fetched == 4 {
+            return Err(anyhow!("connection reset"));
+        }
+        fetch(signature)
+    });
+    assert!(result.is_err());
+    let checkpoint = store.checkpoint(SOURCE).unwrap().unwrap();
+    assert_eq!(checkpoint, signatures[2]);
+
+    // The next run asks for what came after the checkpoint, as `signatures_since` does
+    let resume_at = signatures.iter().position(|signature| *signature == checkpoint).unwrap() + 1;
+    index_signatures(&mut store, &PROGRAM_ID, SOURCE, &signatures[resume_at..], fetch).unwrap();
+
+    assert_eq!(store.checkpoint(SOURCE).unwrap().as_ref(), signatures.last());
+    assert_eq!(row_counts(&db), row_counts(&straight_db));
+    assert_eq!(event_rows(&db), event_rows(&straight_db));
+}
+
+#[test]
+fn failed_transactions_are_recorded_without_events() {
+    let dir = TempDir::new().unwrap();
+    let (mut store, db) = open(&dir);
+    let txs = history();
+    let failed = txs.iter().find(|tx| tx.failed).unwrap();
+    // Its logs still hold the commission credited before the payout failed
+    assert!(failed.logs.iter().any(|line| line.starts_with("Program data: ")));
+
+    import(&mut store, &txs);
+    let (recorded, events, commissions): (bool, i64, i64) = db
+        .query_row(
+            "SELECT failed, (SELECT COUNT(*) FROM events WHERE signature = ?1), (SELECT COUNT(*) FROM commissions WHERE signature = ?1)
+             FROM transactions WHERE signature = ?1",
+            [&failed.signature],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((recorded, events, commissions), (true, 0, 0));
+}
+
+#[test]
+fn a_restaking_claim_is_counted_once() {
+    let dir = TempDir::new().unwrap();
+    let (mut store, db) = open(&dir);
+    let txs = history();
+    import(&mut store, &txs);
+
+    // `RewardsRestaked` followed by `RewardsClaimed` is one claim, booked as the restake
+    let restake = txs.iter().find(|tx| event_names(tx).contains(&"RewardsRestaked")).unwrap();
+    assert_eq!(event_names(restake), ["CommissionCredited", "RewardsRestaked", "RewardsClaimed"]);
+    let mut query = db.prepare("SELECT kind, amount FROM claims WHERE signature = ?1").unwrap();
+    let claims: Vec<(String, u64)> = query.query_map([&restake.signature], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(Result::unwrap).collect();
+    assert_eq!(claims, [("restake".to_owned(), 1_728_000)]);
+
+    // The plain claim that follows is booked as a reward
+    let kinds: Vec<String> = db.prepare("SELECT kind FROM claims ORDER BY timestamp").unwrap().query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+    assert_eq!(kinds, ["restake", "reward"]);
+}
+
+fn event_names(tx: &TxRecord) -> Vec<&'static str> {
+    stake_client::events::parse_logs(&PROGRAM_ID, &tx.logs).iter().map(|event| event.name()).collect()
+}
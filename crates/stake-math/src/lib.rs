@@ -0,0 +1,11 @@
+//! Reward, lock and commission formulas of the referral staking program, `no_std` and
+//! dependency-free so off-chain code computes exactly what the program does.
+//!
+//! The formulas live in `reward.rs`, which the program compiles in directly as its
+//! `reward_math` module; this crate is the same file for everyone else.
+
+#![no_std]
+
+mod reward;
+
+pub use reward::*;
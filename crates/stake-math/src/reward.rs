@@ -0,0 +1,82 @@
+// Compiled both as `stake_math` and, via `#[path]`, as the program's `reward_math` module:
+// keep this file free of `crate::` paths and dependencies.
+
+/// Fixed-point scale of `reward_per_token` values.
+pub const PRECISION: u128 = 1_000_000_000;
+pub const BASIS_POINTS: u16 = 10000;
+
+/// Pool reward accumulator: reward per staked token (scaled by `PRECISION`) and when it was last advanced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accumulator {
+    pub reward_per_token_stored: u128,
+    pub last_update_time: i64,
+}
+
+impl Accumulator {
+    /// Advances the accumulator to `now`; nothing accrues past `end_time` (0 = open-ended)
+    /// or while nothing is staked. Saturates at `u128::MAX` instead of overflowing, so the
+    /// value never decreases.
+    pub fn update(self, reward_rate: u64, total_staked: u64, end_time: i64, now: i64) -> Self {
+        let now = if end_time > 0 { now.min(end_time) } else { now };
+        if total_staked == 0 {
+            return Self { last_update_time: self.last_update_time.max(now), ..self };
+        }
+        let delta = now.checked_sub(self.last_update_time).unwrap_or(0);
+        if delta <= 0 {
+            return self;
+        }
+        let reward_inc = (reward_rate as u128 * delta as u128).saturating_mul(PRECISION) / total_staked as u128;
+        Self {
+            reward_per_token_stored: self.reward_per_token_stored.saturating_add(reward_inc),
+            last_update_time: now,
+        }
+    }
+
+    /// Applies a schedule change from `old_end_time` to `new_end_time` to an accumulator already advanced
+    /// to `now` under the old one. If the pool had ended and the change extends or reopens it, accrual
+    /// restarts at `now` rather than paying for the closed gap since `old_end_time`.
+    pub fn reschedule(self, old_end_time: i64, new_end_time: i64, now: i64) -> Self {
+        let ended = old_end_time > 0 && old_end_time <= now;
+        let extended = new_end_time == 0 || new_end_time > old_end_time;
+        if ended && extended {
+            return Self { last_update_time: self.last_update_time.max(now), ..self };
+        }
+        self
+    }
+}
+
+/// Reward earned by `staked_amount` since its checkpoint `reward_per_token_paid`, capped at `u64::MAX`.
+pub fn pending_reward(staked_amount: u64, reward_per_token_stored: u128, reward_per_token_paid: u128) -> u64 {
+    let delta = reward_per_token_stored.saturating_sub(reward_per_token_paid);
+    u64::try_from((staked_amount as u128).saturating_mul(delta) / PRECISION).unwrap_or(u64::MAX)
+}
+
+/// When a stake last (re)started its lock at `last_stake_time` becomes withdrawable.
+pub fn unlock_time(last_stake_time: i64, lock_duration: i64) -> Option<i64> {
+    last_stake_time.checked_add(lock_duration)
+}
+
+/// New `last_stake_time` after adding `amount` to a stake of `staked_amount`: the remaining lock
+/// of the old stake and a full lock for the new amount, blended by weight.
+pub fn weighted_last_stake_time(staked_amount: u64, last_stake_time: i64, amount: u64, lock_duration: i64, now: i64) -> i64 {
+    if staked_amount == 0 {
+        return now;
+    }
+    let unlock_time = unlock_time(last_stake_time, lock_duration).unwrap_or(i64::MAX);
+    let remaining_time = if unlock_time > now { unlock_time - now } else { 0 };
+    let old_weight = staked_amount as u128 * remaining_time as u128;
+    let new_weight = amount as u128 * lock_duration as u128;
+    let total_amount = staked_amount as u128 + amount as u128;
+    let weighted_remaining = (old_weight + new_weight) / total_amount;
+    now.checked_sub(lock_duration - weighted_remaining as i64).unwrap_or(now)
+}
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn bps_share(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BASIS_POINTS as u128) as u64
+}
+
+/// L1-L3 commission on `reward` at the pool's per-level rates.
+pub fn commission_split(reward: u64, level_bps: [u16; 3]) -> [u64; 3] {
+    level_bps.map(|bps| bps_share(reward, bps))
+}
@@ -0,0 +1,138 @@
+use proptest::prelude::*;
+use stake_math::{bps_share, commission_split, pending_reward, weighted_last_stake_time, Accumulator, BASIS_POINTS, PRECISION};
+
+/// The program caps the three referral levels at 2500 bps combined.
+const MAX_REFERRAL_BPS: u16 = 2500;
+
+fn referral_bps() -> impl Strategy<Value = [u16; 3]> {
+    (0..=MAX_REFERRAL_BPS)
+        .prop_flat_map(|l1| (Just(l1), 0..=MAX_REFERRAL_BPS - l1))
+        .prop_flat_map(|(l1, l2)| (Just(l1), Just(l2), 0..=MAX_REFERRAL_BPS - l1 - l2))
+        .prop_map(|(l1, l2, l3)| [l1, l2, l3])
+}
+
+proptest! {
+    #[test]
+    fn accumulator_never_decreases(
+        stored in any::<u128>(),
+        last_update_time in any::<i64>(),
+        reward_rate in any::<u64>(),
+        total_staked in any::<u64>(),
+        end_time in any::<i64>(),
+        now in any::<i64>(),
+    ) {
+        let before = Accumulator { reward_per_token_stored: stored, last_update_time };
+        let after = before.update(reward_rate, total_staked, end_time, now);
+        prop_assert!(after.reward_per_token_stored >= before.reward_per_token_stored);
+        prop_assert!(after.last_update_time >= before.last_update_time);
+    }
+
+    #[test]
+    fn accumulator_is_monotonic_in_time(
+        reward_rate in any::<u64>(),
+        total_staked in 1..=u64::MAX,
+        start in 0..i64::MAX / 2,
+        first in 0..i64::MAX / 4,
+        second in 0..i64::MAX / 4,
+    ) {
+        let start = Accumulator { reward_per_token_stored: 0, last_update_time: start };
+        let earlier = start.update(reward_rate, total_staked, 0, start.last_update_time + first);
+        let later = earlier.update(reward_rate, total_staked, 0, earlier.last_update_time + second);
+        prop_assert!(later.reward_per_token_stored >= earlier.reward_per_token_stored);
+    }
+
+    #[test]
+    fn reopening_an_ended_schedule_pays_nothing_for_the_gap(
+        reward_rate in any::<u64>(),
+        total_staked in 1..=u64::MAX,
+        start in 0..i64::MAX / 4,
+        old_duration in 1..i64::MAX / 8,
+        gap in 0..i64::MAX / 8,
+        new_end_offset in prop::option::of(1..i64::MAX / 8),
+        after in 0..i64::MAX / 8,
+    ) {
+        let old_end_time = start + old_duration;
+        let now = old_end_time + gap;
+        // Open-ended, or a new end anywhere after the old one
+        let new_end_time = new_end_offset.map_or(0, |offset| old_end_time + offset);
+        let settled = Accumulator { reward_per_token_stored: 0, last_update_time: start }.update(reward_rate, total_staked, old_end_time, now);
+        let rescheduled = settled.reschedule(old_end_time, new_end_time, now);
+        prop_assert_eq!(rescheduled.reward_per_token_stored, settled.reward_per_token_stored);
+
+        // Whatever accrues later is what a schedule starting at `now` would have paid
+        let later = now + after;
+        let reopened_from_now = Accumulator { reward_per_token_stored: settled.reward_per_token_stored, last_update_time: now };
+        prop_assert_eq!(
+            rescheduled.update(reward_rate, total_staked, new_end_time, later),
+            reopened_from_now.update(reward_rate, total_staked, new_end_time, later),
+        );
+    }
+
+    #[test]
+    fn reschedule_is_a_no_op_while_the_schedule_runs(
+        stored in any::<u128>(),
+        last_update_time in any::<i64>(),
+        old_end_time in any::<i64>(),
+        new_end_time in any::<i64>(),
+        now in any::<i64>(),
+    ) {
+        prop_assume!(old_end_time == 0 || old_end_time > now);
+        let accumulator = Accumulator { reward_per_token_stored: stored, last_update_time };
+        prop_assert_eq!(accumulator.reschedule(old_end_time, new_end_time, now), accumulator);
+    }
+
+    #[test]
+    fn pending_reward_is_monotonic(
+        staked_amount in any::<u64>(),
+        paid in any::<u128>(),
+        increase in any::<u128>(),
+        more in any::<u128>(),
+    ) {
+        let stored = paid.saturating_add(increase);
+        let later = stored.saturating_add(more);
+        prop_assert!(pending_reward(staked_amount, later, paid) >= pending_reward(staked_amount, stored, paid));
+        prop_assert!(pending_reward(staked_amount.saturating_add(1), stored, paid) >= pending_reward(staked_amount, stored, paid));
+    }
+
+    #[test]
+    fn pending_reward_is_exact_when_it_fits(staked_amount in any::<u64>(), delta in 0..=PRECISION * u64::MAX as u128) {
+        match (staked_amount as u128).checked_mul(delta).map(|product| product / PRECISION) {
+            Some(exact) if exact <= u64::MAX as u128 => prop_assert_eq!(pending_reward(staked_amount, delta, 0) as u128, exact),
+            _ => prop_assert_eq!(pending_reward(staked_amount, delta, 0), u64::MAX),
+        }
+    }
+
+    #[test]
+    fn bps_share_rounds_down(amount in any::<u64>(), bps in 0..=BASIS_POINTS) {
+        let share = bps_share(amount, bps) as u128;
+        let product = amount as u128 * bps as u128;
+        prop_assert!(share * BASIS_POINTS as u128 <= product);
+        prop_assert!(product < (share + 1) * BASIS_POINTS as u128);
+    }
+
+    #[test]
+    fn commission_split_never_exceeds_reward(reward in any::<u64>(), level_bps in referral_bps()) {
+        let parts = commission_split(reward, level_bps);
+        let total: u128 = parts.iter().map(|part| *part as u128).sum();
+        prop_assert!(total <= reward as u128);
+    }
+
+    #[test]
+    fn weighted_last_stake_time_stays_in_bounds(
+        staked_amount in any::<u64>(),
+        amount in any::<u64>(),
+        lock_duration in 0..=i64::MAX / 2,
+        now in 0..=i64::MAX / 2,
+        age in any::<i64>(),
+    ) {
+        let last_stake_time = now - age.rem_euclid(now + 1);
+        let blended = weighted_last_stake_time(staked_amount, last_stake_time, amount, lock_duration, now);
+        prop_assert!(blended <= now);
+        if staked_amount == 0 {
+            prop_assert_eq!(blended, now);
+        } else {
+            // Never shortens the old lock, never locks the new amount longer than a fresh stake.
+            prop_assert!(blended >= last_stake_time.max(now - lock_duration));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+#!/usr/bin/env bash
+# Builds Contract/StakingWithRefferal.rs into tests/fixtures/solana_staking_referral.so for the
+# solana-program-test suite. Needs the Solana CLI for `cargo build-sbf`.
+#
+# The program is a single source file with no manifest of its own, so one is generated under
+# target/ with the contract as its library root; `mod merkle` and the `#[path]` to stake-math
+# resolve from there as they do in any other Anchor build of it.
+set -euo pipefail
+
+here="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+repo="$(cd "$here/../.." && pwd)"
+build_dir="$here/target/program-build"
+mkdir -p "$build_dir" "$here/tests/fixtures"
+
+cat > "$build_dir/Cargo.toml" <<TOML
+[package]
+name = "solana-staking-referral"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[lib]
+name = "solana_staking_referral"
+path = "$repo/Contract/StakingWithRefferal.rs"
+crate-type = ["cdylib", "lib"]
+
+[features]
+default = []
+no-entrypoint = []
+no-idl = []
+no-log-ix-name = []
+cpi = ["no-entrypoint"]
+
+[dependencies]
+anchor-lang = { version = "0.29", features = ["init-if-needed"] }
+anchor-spl = "0.29"
+
+[workspace]
+TOML
+
+cargo build-sbf --manifest-path "$build_dir/Cargo.toml" --sbf-out-dir "$here/tests/fixtures"
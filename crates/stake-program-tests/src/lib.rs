@@ -0,0 +1,217 @@
+//! Harness that runs the built program in `solana-program-test`.
+//!
+//! The tests need `solana_staking_referral.so`. `build-program.sh` builds it into `tests/fixtures` with
+//! `cargo build-sbf` (or point `SBF_OUT_DIR` at a build of your own). Because of that the tests are
+//! `#[ignore]`d; run them with `cargo test -- --ignored`, as the `program-tests` CI workflow does.
+//!
+//! The program is deployed under the upgradeable loader with [`TestEnv::authority`] as its upgrade
+//! authority, so `initialize_registry` accepts it the way it would on a real deploy.
+
+use solana_program_test::{find_file, read_file, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use stake_client::{
+    instruction::{self, PoolKeys},
+    pda,
+    state::{PoolRegistry, ProgramAccount},
+    PROGRAM_ID,
+};
+
+const IDL: &str = include_str!("../../../lib/idl.json");
+
+pub const PROGRAM_SO: &str = "solana_staking_referral.so";
+
+/// Lamports every wallet from [`TestEnv::wallet`] starts with.
+pub const WALLET_LAMPORTS: u64 = 10_000_000_000;
+
+pub const DECIMALS: u8 = 6;
+
+/// Anchor's `ConstraintSeeds`: an account isn't at the address its `seeds` constraint derives.
+pub const CONSTRAINT_SEEDS: u32 = 2006;
+
+/// Code of `StakingError::<name>`, from the `errors` of the program's IDL.
+pub fn staking_error(name: &str) -> u32 {
+    let idl: serde_json::Value = serde_json::from_str(IDL).expect("lib/idl.json is valid JSON");
+    idl["errors"]
+        .as_array()
+        .expect("the IDL lists the program's errors")
+        .iter()
+        .find(|error| error["name"] == name)
+        .and_then(|error| error["code"].as_u64())
+        .unwrap_or_else(|| panic!("no StakingError::{name} in lib/idl.json")) as u32
+}
+
+/// Asserts `result` failed with the program (or Anchor) error `code`.
+#[track_caller]
+pub fn assert_error(result: Result<(), TransactionError>, code: u32) {
+    match result {
+        Err(TransactionError::InstructionError(_, InstructionError::Custom(actual))) => assert_eq!(actual, code),
+        other => panic!("expected custom error {code}, got {other:?}"),
+    }
+}
+
+pub struct TestEnv {
+    pub context: ProgramTestContext,
+    /// Upgrade authority of the program, and authority of every pool from [`TestEnv::create_pool`].
+    pub authority: Keypair,
+}
+
+impl TestEnv {
+    pub async fn start() -> Self {
+        Self::start_with_accounts(Vec::new()).await
+    }
+
+    /// Starts with `accounts` already in genesis, e.g. old-layout accounts to migrate.
+    pub async fn start_with_accounts(accounts: Vec<(Pubkey, Account)>) -> Self {
+        let authority = Keypair::new();
+        let mut program_test = ProgramTest::default();
+        add_upgradeable_program(&mut program_test, authority.pubkey());
+        program_test.add_account(authority.pubkey(), Account::new(WALLET_LAMPORTS, 0, &system_program::ID));
+        for (address, account) in accounts {
+            program_test.add_account(address, account);
+        }
+        let context = program_test.start_with_context().await;
+        Self { context, authority }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Sends `instructions` in one transaction paid by the test payer.
+    pub async fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), TransactionError> {
+        // A fresh blockhash keeps a resent identical transaction from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.payer()), &all_signers, blockhash);
+        self.context.banks_client.process_transaction(transaction).await.map_err(|err| err.unwrap())
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    /// Moves the clock's `unix_timestamp` forward by `seconds`; the slot stays where it is.
+    pub async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn raw_account(&mut self, address: Pubkey) -> Option<Account> {
+        self.context.banks_client.get_account(address).await.unwrap()
+    }
+
+    pub async fn account<T: ProgramAccount>(&mut self, address: Pubkey) -> T {
+        let account = self.raw_account(address).await.unwrap_or_else(|| panic!("{address} doesn't exist"));
+        T::try_from_account_data(&account.data).unwrap()
+    }
+
+    pub async fn token_balance(&mut self, token_account: Pubkey) -> u64 {
+        let account = self.raw_account(token_account).await.unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    /// A new keypair holding [`WALLET_LAMPORTS`].
+    pub async fn wallet(&mut self) -> Keypair {
+        let wallet = Keypair::new();
+        let transfer = system_instruction::transfer(&self.payer(), &wallet.pubkey(), WALLET_LAMPORTS);
+        self.send(&[transfer], &[]).await.unwrap();
+        wallet
+    }
+
+    /// An SPL Token mint with [`TestEnv::authority`] as mint authority.
+    pub async fn create_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let lamports = Rent::default().minimum_balance(spl_token::state::Mint::LEN);
+        let instructions = [
+            system_instruction::create_account(&self.payer(), &mint.pubkey(), lamports, spl_token::state::Mint::LEN as u64, &spl_token::ID),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &self.authority.pubkey(), None, DECIMALS).unwrap(),
+        ];
+        self.send(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// A token account of `owner` for `mint` holding `amount`.
+    pub async fn token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let account = Keypair::new();
+        let authority = self.authority.insecure_clone();
+        let lamports = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+        let mut instructions = vec![
+            system_instruction::create_account(&self.payer(), &account.pubkey(), lamports, spl_token::state::Account::LEN as u64, &spl_token::ID),
+            spl_token::instruction::initialize_account3(&spl_token::ID, &account.pubkey(), &mint, &owner).unwrap(),
+        ];
+        let mut signers = vec![&account];
+        // Signing with a key no instruction asks for fails, so the mint authority only signs a mint
+        if amount > 0 {
+            instructions.push(spl_token::instruction::mint_to(&spl_token::ID, &mint, &account.pubkey(), &authority.pubkey(), &[], amount).unwrap());
+            signers.push(&authority);
+        }
+        self.send(&instructions, &signers).await.unwrap();
+        account.pubkey()
+    }
+
+    /// Creates the pool registry if it doesn't exist yet.
+    pub async fn initialize_registry(&mut self) {
+        if self.raw_account(pda::pool_registry(&PROGRAM_ID).0).await.is_some() {
+            return;
+        }
+        let authority = self.authority.insecure_clone();
+        self.send(&[instruction::initialize_registry(PROGRAM_ID, authority.pubkey())], &[&authority]).await.unwrap();
+    }
+
+    /// Registers the next pool for `token_mint` with referrals on, its reward vault holding `rewards`.
+    pub async fn create_pool(&mut self, token_mint: Pubkey, reward_mint: Pubkey, rewards: u64) -> PoolKeys {
+        self.initialize_registry().await;
+        let registry: PoolRegistry = self.account(pda::pool_registry(&PROGRAM_ID).0).await;
+        let authority = self.authority.insecure_clone();
+        let keys = PoolKeys::derive(PROGRAM_ID, token_mint, reward_mint, registry.pool_count, authority.pubkey(), spl_token::ID, spl_token::ID);
+        let initialize = instruction::initialize(&keys, 1_000, true, format!("pool {}", keys.pool_id), 0, 0);
+        self.send(&[initialize], &[&authority]).await.unwrap();
+
+        let funder_account = self.token_account(reward_mint, authority.pubkey(), rewards).await;
+        self.send(&[instruction::add_rewards(&keys, authority.pubkey(), funder_account, rewards)], &[&authority]).await.unwrap();
+        keys
+    }
+}
+
+/// Deploys the program the way `solana program deploy` does: a `Program` account pointing at a
+/// `ProgramData` account that holds the ELF and the upgrade authority.
+fn add_upgradeable_program(program_test: &mut ProgramTest, upgrade_authority: Pubkey) {
+    let path = find_file(PROGRAM_SO).unwrap_or_else(|| panic!("{PROGRAM_SO} not found; set SBF_OUT_DIR to the directory it was built to"));
+    let elf = read_file(path);
+    let rent = Rent::default();
+    let programdata_address = pda::program_data(&PROGRAM_ID);
+
+    let mut program = Account::new_data(
+        rent.minimum_balance(UpgradeableLoaderState::size_of_program()),
+        &UpgradeableLoaderState::Program { programdata_address },
+        &bpf_loader_upgradeable::ID,
+    )
+    .unwrap();
+    program.executable = true;
+    program_test.add_account(PROGRAM_ID, program);
+
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let mut program_data = Account::new_data_with_space(
+        rent.minimum_balance(metadata_len + elf.len()),
+        &UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(upgrade_authority) },
+        metadata_len + elf.len(),
+        &bpf_loader_upgradeable::ID,
+    )
+    .unwrap();
+    program_data.data[metadata_len..].copy_from_slice(&elf);
+    program_test.add_account(programdata_address, program_data);
+}
@@ -0,0 +1,184 @@
+//! Differential test of the reward accounting: random sequences of stakes, unstakes, claims and clock
+//! advances run through the program and through a model built on `stake_math`, compared after every step.
+
+use proptest::prelude::*;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use stake_client::{
+    instruction::{self, PoolKeys},
+    state::{StakingPool, UserStake},
+    upline::{LinkAccounts, Upline},
+};
+use stake_math::{pending_reward, Accumulator};
+use stake_program_tests::{assert_error, staking_error, TestEnv};
+
+const USERS: usize = 3;
+const MIN_STAKE: u64 = 1_000_000;
+const REWARD_RATE: u64 = 1_000;
+const REWARDS: u64 = 1_000_000_000_000;
+const STEPS: usize = 12;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Advance(i64),
+    Stake { user: usize, amount: u64 },
+    /// Unstakes `per_mille` thousandths of the user's stake.
+    Unstake { user: usize, per_mille: u64 },
+    Claim { user: usize },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1..86_400i64).prop_map(Op::Advance),
+        (0..USERS, MIN_STAKE..=10 * MIN_STAKE).prop_map(|(user, amount)| Op::Stake { user, amount }),
+        (0..USERS, 1..=1_000u64).prop_map(|(user, per_mille)| Op::Unstake { user, per_mille }),
+        (0..USERS).prop_map(|user| Op::Claim { user }),
+    ]
+}
+
+#[derive(Clone, Copy, Default)]
+struct ModelStake {
+    exists: bool,
+    staked_amount: u64,
+    reward_per_token_paid: u128,
+    reward_pending: u64,
+}
+
+/// The pool as `stake_math` says it should be.
+#[derive(Clone)]
+struct Model {
+    accumulator: Accumulator,
+    total_staked: u64,
+    reward_liability: u64,
+    staking_vault: u64,
+    reward_vault: u64,
+    stakes: [ModelStake; USERS],
+}
+
+impl Model {
+    /// What `update_rewards_optimized` does before every stake, unstake and claim.
+    fn settle(&mut self, user: usize, now: i64) {
+        self.accumulator = self.accumulator.update(REWARD_RATE, self.total_staked, 0, now);
+        let stake = &mut self.stakes[user];
+        if stake.staked_amount > 0 {
+            let pending = pending_reward(stake.staked_amount, self.accumulator.reward_per_token_stored, stake.reward_per_token_paid);
+            stake.reward_pending += pending;
+            self.reward_liability += pending;
+        }
+        stake.reward_per_token_paid = self.accumulator.reward_per_token_stored;
+    }
+}
+
+struct User {
+    wallet: Keypair,
+    token_account: Pubkey,
+    reward_account: Pubkey,
+}
+
+async fn assert_matches(env: &mut TestEnv, keys: &PoolKeys, users: &[User], model: &Model, step: usize) {
+    let pool: StakingPool = env.account(keys.pool).await;
+    assert_eq!(pool.reward_per_token_stored, model.accumulator.reward_per_token_stored, "reward_per_token_stored after step {step}");
+    assert_eq!(pool.last_update_time, model.accumulator.last_update_time, "last_update_time after step {step}");
+    assert_eq!(pool.total_staked, model.total_staked, "total_staked after step {step}");
+    assert_eq!(pool.reward_liability, model.reward_liability, "reward_liability after step {step}");
+    assert_eq!(env.token_balance(keys.staking_vault).await, model.staking_vault, "staking vault after step {step}");
+    assert_eq!(env.token_balance(keys.reward_vault).await, model.reward_vault, "reward vault after step {step}");
+    for (user, expected) in users.iter().zip(&model.stakes).filter(|(_, stake)| stake.exists) {
+        let stake: UserStake = env.account(keys.user_stake(&user.wallet.pubkey())).await;
+        assert_eq!(stake.staked_amount, expected.staked_amount, "staked_amount after step {step}");
+        assert_eq!(stake.reward_per_token_paid, expected.reward_per_token_paid, "reward_per_token_paid after step {step}");
+        assert_eq!(stake.reward_pending, expected.reward_pending, "reward_pending after step {step}");
+    }
+}
+
+async fn run(ops: Vec<Op>) {
+    let mut env = TestEnv::start().await;
+    let token_mint = env.create_mint().await;
+    let reward_mint = env.create_mint().await;
+    let keys = env.create_pool(token_mint, reward_mint, REWARDS).await;
+    // Unstakes are compared at any point, not only after the lock
+    let authority = env.authority.insecure_clone();
+    env.send(&[instruction::update_lock_duration(&keys, 0)], &[&authority]).await.unwrap();
+
+    let mut users = Vec::new();
+    for _ in 0..USERS {
+        let wallet = env.wallet().await;
+        let token_account = env.token_account(token_mint, wallet.pubkey(), STEPS as u64 * 10 * MIN_STAKE).await;
+        let reward_account = env.token_account(reward_mint, wallet.pubkey(), 0).await;
+        users.push(User { wallet, token_account, reward_account });
+    }
+
+    let pool: StakingPool = env.account(keys.pool).await;
+    let mut model = Model {
+        accumulator: Accumulator { reward_per_token_stored: pool.reward_per_token_stored, last_update_time: pool.last_update_time },
+        total_staked: 0,
+        reward_liability: 0,
+        staking_vault: 0,
+        reward_vault: REWARDS,
+        stakes: [ModelStake::default(); USERS],
+    };
+
+    for (step, op) in ops.into_iter().enumerate() {
+        let now = env.now().await;
+        match op {
+            Op::Advance(seconds) => env.advance_clock(seconds).await,
+            Op::Stake { user, amount } => {
+                let User { wallet, token_account, .. } = &users[user];
+                let stake = instruction::stake_with_referral(&keys, wallet.pubkey(), Some(*token_account), amount, None, None, &LinkAccounts::default(), None);
+                env.send(&[stake], &[wallet]).await.unwrap();
+                model.stakes[user].exists = true;
+                model.settle(user, now);
+                model.stakes[user].staked_amount += amount;
+                model.total_staked += amount;
+                model.staking_vault += amount;
+            }
+            Op::Unstake { user, per_mille } => {
+                let amount = model.stakes[user].staked_amount * per_mille / 1_000;
+                // Only stakes that exist, and amounts the program accepts
+                if amount == 0 {
+                    continue;
+                }
+                let User { wallet, token_account, .. } = &users[user];
+                let unstake = instruction::unstake(&keys, wallet.pubkey(), Some(*token_account), &Upline::default(), amount);
+                env.send(&[unstake], &[wallet]).await.unwrap();
+                model.settle(user, now);
+                model.stakes[user].staked_amount -= amount;
+                model.total_staked -= amount;
+                model.staking_vault -= amount;
+            }
+            Op::Claim { user } => {
+                if !model.stakes[user].exists {
+                    continue;
+                }
+                let User { wallet, reward_account, .. } = &users[user];
+                let claim = instruction::claim_rewards(&keys, wallet.pubkey(), *reward_account, &Upline::default(), None);
+                let result = env.send(&[claim], &[wallet]).await;
+                let mut settled = model.clone();
+                settled.settle(user, now);
+                let reward = settled.stakes[user].reward_pending;
+                if reward == 0 {
+                    // Nothing to pay: the program refuses and keeps nothing of the settlement
+                    assert_error(result, staking_error("NoRewardsToClaim"));
+                    continue;
+                }
+                result.unwrap();
+                model = settled;
+                model.stakes[user].reward_pending = 0;
+                model.reward_liability -= reward;
+                model.reward_vault -= reward;
+            }
+        }
+        assert_matches(&mut env, &keys, &users, &model, step).await;
+    }
+}
+
+proptest! {
+    // Every case boots a fresh bank, so keep the count low
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    #[ignore = "needs the built program; see the crate docs"]
+    fn program_matches_stake_math(ops in prop::collection::vec(op(), 1..=STEPS)) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(run(ops));
+    }
+}
@@ -0,0 +1,306 @@
+//! Migration of accounts written by the original program: the byte layouts below are the ones it
+//! allocated and serialized, before the registry, per-pool referrer stats and versioning.
+
+use borsh::BorshSerialize;
+use solana_sdk::{account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent, signature::Keypair, signer::Signer};
+use stake_client::{
+    instruction::{self, MigrateKind, PoolKeys},
+    pda,
+    state::{PoolRegistry, PoolRegistryEntry, PoolStatus, ProgramAccount, ReferrerStats, StakingPool, UserStake, PAUSE_ALL, PAUSE_REFERRAL_CLAIM},
+    PROGRAM_ID,
+};
+use stake_program_tests::{assert_error, staking_error, TestEnv, DECIMALS};
+
+#[derive(BorshSerialize)]
+struct StakingPoolV0 {
+    authority: Pubkey,
+    token_mint: Pubkey,
+    reward_mint: Pubkey,
+    staking_vault: Pubkey,
+    reward_vault: Pubkey,
+    fee_receiver: Pubkey,
+    reward_per_token_stored: u128,
+    reward_rate: u64,
+    total_staked: u64,
+    fee_stake: u64,
+    fee_unstake: u64,
+    fee_claim: u64,
+    min_stake_amount: u64,
+    last_update_time: i64,
+    lock_duration: i64,
+    referral_l1_bps: u16,
+    referral_l2_bps: u16,
+    referral_l3_bps: u16,
+    referral_enabled: bool,
+    paused: bool,
+    bump: u8,
+}
+
+#[derive(BorshSerialize)]
+struct UserStakeV0 {
+    user: Pubkey,
+    pool: Pubkey,
+    staked_amount: u64,
+    reward_per_token_paid: u128,
+    reward_pending: u64,
+    last_stake_time: i64,
+    referrer: Option<Pubkey>,
+    referrer_l2: Option<Pubkey>,
+    referrer_l3: Option<Pubkey>,
+    total_earned: u64,
+}
+
+#[derive(BorshSerialize)]
+struct ReferrerStatsV0 {
+    referrer: Pubkey,
+    total_referrals: u64,
+    total_commission_earned: u64,
+    pending_rewards: u64,
+    volume_referred: u64,
+    active_stake_l1: u64,
+    active_stake_l2: u64,
+    active_stake_l3: u64,
+}
+
+// Space the original program allocated after the discriminator
+const STAKING_POOL_V0_SIZE: usize = 288;
+const USER_STAKE_V0_SIZE: usize = 220;
+const REFERRER_STATS_V0_SIZE: usize = 96;
+
+/// A rent-exempt program account holding `T`'s discriminator and `body`, zero-padded to `space`.
+fn baseline_account<T: ProgramAccount>(body: &impl BorshSerialize, space: usize) -> Account {
+    let mut data = T::discriminator().to_vec();
+    body.serialize(&mut data).unwrap();
+    assert!(data.len() <= 8 + space);
+    data.resize(8 + space, 0);
+    Account { lamports: Rent::default().minimum_balance(data.len()), data, owner: PROGRAM_ID, executable: false, rent_epoch: 0 }
+}
+
+/// A rent-exempt SPL Token account (mint or token account) holding `state`.
+fn token_program_account<T: Pack>(state: T) -> Account {
+    let mut data = vec![0; T::LEN];
+    state.pack_into_slice(&mut data);
+    Account { lamports: Rent::default().minimum_balance(T::LEN), data, owner: spl_token::ID, executable: false, rent_epoch: 0 }
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn baseline_accounts_migrate_in_place() {
+    let token_mint = Pubkey::new_unique();
+    let reward_mint = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let (pool, pool_bump) = pda::legacy_staking_pool(&PROGRAM_ID, &token_mint);
+    let old_pool = StakingPoolV0 {
+        authority: authority.pubkey(),
+        token_mint,
+        reward_mint,
+        staking_vault: pda::staking_vault(&PROGRAM_ID, &pool).0,
+        reward_vault: pda::reward_vault(&PROGRAM_ID, &pool).0,
+        fee_receiver: Pubkey::new_unique(),
+        reward_per_token_stored: 123_456_789_000_000_000,
+        reward_rate: 1_000,
+        total_staked: 50_000_000,
+        fee_stake: 1_000_000,
+        fee_unstake: 5_000_000,
+        fee_claim: 100_000,
+        min_stake_amount: 1_000_000,
+        last_update_time: 1_700_000_000,
+        lock_duration: 2_592_000,
+        referral_l1_bps: 1000,
+        referral_l2_bps: 500,
+        referral_l3_bps: 300,
+        referral_enabled: true,
+        paused: true,
+        bump: pool_bump,
+    };
+
+    let user = Pubkey::new_unique();
+    let referrer_keypair = Keypair::new();
+    let referrer = referrer_keypair.pubkey();
+    let referrer_l2 = Pubkey::new_unique();
+    let user_stake = pda::user_stake(&PROGRAM_ID, &user, &pool).0;
+    let old_stake = UserStakeV0 {
+        user,
+        pool,
+        staked_amount: 50_000_000,
+        reward_per_token_paid: 100_000_000_000_000_000,
+        reward_pending: 4_200,
+        last_stake_time: 1_699_000_000,
+        referrer: Some(referrer),
+        referrer_l2: Some(referrer_l2),
+        referrer_l3: None,
+        total_earned: 9_000,
+    };
+
+    // Stats were global then, at ["referrer_stats", referrer]
+    let legacy_stats = pda::legacy_referrer_stats(&PROGRAM_ID, &referrer).0;
+    let old_stats = ReferrerStatsV0 {
+        referrer,
+        total_referrals: 3,
+        total_commission_earned: 900,
+        pending_rewards: 300,
+        volume_referred: 70_000_000,
+        active_stake_l1: 50_000_000,
+        active_stake_l2: 20_000_000,
+        active_stake_l3: 0,
+    };
+
+    // The reward vault holds what the old program owed, so the migrated commission can be claimed
+    let vault_balance = old_stake.reward_pending + old_stats.pending_rewards;
+    let reward_mint_state = spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: vault_balance,
+        decimals: DECIMALS,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let reward_vault_state = spl_token::state::Account {
+        mint: reward_mint,
+        owner: pool,
+        amount: vault_balance,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+
+    let mut env = TestEnv::start_with_accounts(vec![
+        (pool, baseline_account::<StakingPool>(&old_pool, STAKING_POOL_V0_SIZE)),
+        (user_stake, baseline_account::<UserStake>(&old_stake, USER_STAKE_V0_SIZE)),
+        (legacy_stats, baseline_account::<ReferrerStats>(&old_stats, REFERRER_STATS_V0_SIZE)),
+        (reward_mint, token_program_account(reward_mint_state)),
+        (old_pool.reward_vault, token_program_account(reward_vault_state)),
+    ])
+    .await;
+    env.initialize_registry().await;
+    let payer = env.payer();
+
+    let migrate_pool = |pool_id| instruction::migrate(PROGRAM_ID, MigrateKind::StakingPool { pool_id }, pool, payer);
+    env.send(&[migrate_pool(0)], &[]).await.unwrap();
+    let expected_pool = StakingPool {
+        pool_id: 0,
+        authority: old_pool.authority,
+        token_mint,
+        reward_mint,
+        staking_vault: old_pool.staking_vault,
+        reward_vault: old_pool.reward_vault,
+        fee_receiver: old_pool.fee_receiver,
+        reward_per_token_stored: old_pool.reward_per_token_stored,
+        reward_rate: old_pool.reward_rate,
+        total_staked: old_pool.total_staked,
+        fee_stake: old_pool.fee_stake,
+        fee_unstake: old_pool.fee_unstake,
+        fee_claim: old_pool.fee_claim,
+        min_stake_amount: old_pool.min_stake_amount,
+        max_stake_per_wallet: 0,
+        max_total_staked: 0,
+        allowlist_root: [0u8; 32],
+        last_update_time: old_pool.last_update_time,
+        lock_duration: old_pool.lock_duration,
+        min_referee_stake: 0,
+        min_referrer_stake_age: 0,
+        commission_vesting_cliff: 0,
+        commission_vesting_duration: 0,
+        restake_pool: Pubkey::default(),
+        start_time: 0,
+        end_time: 0,
+        reward_liability: 0,
+        sunset: false,
+        emergency_mode: false,
+        referral_l1_bps: old_pool.referral_l1_bps,
+        referral_l2_bps: old_pool.referral_l2_bps,
+        referral_l3_bps: old_pool.referral_l3_bps,
+        referral_enabled: true,
+        strict_referrer_accounts: false,
+        pause_flags: PAUSE_ALL,
+        bump: pool_bump,
+        version: 1,
+        legacy_seeds: true,
+        active_competition: Pubkey::default(),
+        reserved: [0u8; 31],
+    };
+    assert_eq!(env.account::<StakingPool>(pool).await, expected_pool);
+    assert_eq!(env.account::<PoolRegistry>(pda::pool_registry(&PROGRAM_ID).0).await.pool_count, 1);
+    let (pool_entry, entry_bump) = pda::pool_entry(&PROGRAM_ID, 0);
+    let expected_entry = PoolRegistryEntry {
+        pool,
+        pool_id: 0,
+        token_mint,
+        reward_mint,
+        name: String::new(),
+        start_time: 0,
+        end_time: 0,
+        status: PoolStatus::Paused,
+        bump: entry_bump,
+        version: 1,
+        reserved: [0u8; 32],
+    };
+    assert_eq!(env.account::<PoolRegistryEntry>(pool_entry).await, expected_entry);
+
+    let migrate_stake = instruction::migrate(PROGRAM_ID, MigrateKind::UserStake { pool }, user_stake, payer);
+    env.send(std::slice::from_ref(&migrate_stake), &[]).await.unwrap();
+    let expected_stake = UserStake {
+        user,
+        pool,
+        staked_amount: old_stake.staked_amount,
+        reward_per_token_paid: old_stake.reward_per_token_paid,
+        reward_pending: old_stake.reward_pending,
+        last_stake_time: old_stake.last_stake_time,
+        referrer: Some(referrer),
+        referrer_l2: Some(referrer_l2),
+        referrer_l3: None,
+        total_earned: old_stake.total_earned,
+        first_stake_time: old_stake.last_stake_time,
+        allowlist_root: [0u8; 32],
+        allowlist_cap: 0,
+        frozen: false,
+        unstake_frozen: false,
+        version: 1,
+        reserved: [0u8; 32],
+    };
+    assert_eq!(env.account::<UserStake>(user_stake).await, expected_stake);
+    // The stake's settled reward is now owed by the pool
+    assert_eq!(env.account::<StakingPool>(pool).await.reward_liability, old_stake.reward_pending);
+
+    let legacy_rent = env.raw_account(legacy_stats).await.unwrap().lamports;
+    let stats_kind = MigrateKind::ReferrerStats { pool, referrer, authority: authority.pubkey() };
+    let migrate_stats = instruction::migrate(PROGRAM_ID, stats_kind, legacy_stats, payer);
+    env.send(std::slice::from_ref(&migrate_stats), &[&authority]).await.unwrap();
+    let keys = PoolKeys::from_state(PROGRAM_ID, pool, &expected_pool, spl_token::ID, spl_token::ID);
+    let stats = keys.referrer_stats(&referrer);
+    let expected_stats = ReferrerStats {
+        referrer,
+        total_referrals: old_stats.total_referrals,
+        total_commission_earned: old_stats.total_commission_earned,
+        pending_rewards: old_stats.pending_rewards,
+        volume_referred: old_stats.volume_referred,
+        active_stake_l1: old_stats.active_stake_l1,
+        active_stake_l2: old_stats.active_stake_l2,
+        active_stake_l3: old_stats.active_stake_l3,
+        grant_count: 0,
+        flagged: false,
+        frozen: false,
+        version: 2,
+        pool,
+        open_grants: 0,
+        reserved: [0u8; 24],
+    };
+    assert_eq!(env.account::<ReferrerStats>(stats).await, expected_stats);
+    // The old account is closed to the referrer and its pending commission is owed by the pool
+    assert!(env.raw_account(legacy_stats).await.is_none());
+    assert_eq!(env.raw_account(referrer).await.unwrap().lamports, legacy_rent);
+    assert_eq!(env.account::<StakingPool>(pool).await.reward_liability, vault_balance);
+
+    // Running any of them again is refused rather than re-applied
+    assert_error(env.send(&[migrate_pool(1)], &[]).await, staking_error("AlreadyMigrated"));
+    assert_error(env.send(&[migrate_stake], &[]).await, staking_error("AlreadyMigrated"));
+    assert_error(env.send(&[migrate_stats], &[&authority]).await, staking_error("AlreadyMigrated"));
+    assert_eq!(env.account::<StakingPool>(pool).await.reward_liability, vault_balance);
+
+    // The migrated commission is claimable in the pool it was moved into
+    env.send(&[instruction::set_pause_flags(&keys, PAUSE_REFERRAL_CLAIM, false)], &[&authority]).await.unwrap();
+    let referrer_reward_account = env.token_account(reward_mint, referrer, 0).await;
+    let claim = instruction::claim_referral_rewards(&keys, referrer, referrer_reward_account);
+    env.send(&[claim], &[&referrer_keypair]).await.unwrap();
+    assert_eq!(env.token_balance(referrer_reward_account).await, old_stats.pending_rewards);
+    assert_eq!(env.account::<ReferrerStats>(stats).await.pending_rewards, 0);
+    assert_eq!(env.account::<StakingPool>(pool).await.reward_liability, old_stake.reward_pending);
+}
@@ -0,0 +1,393 @@
+//! Account substitution: every pool-scoped account swapped for one that belongs to another pool,
+//! mint, user or referrer has to be rejected, not silently used.
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::TransactionError};
+use stake_client::{
+    instruction::{self, PoolKeys, RestakeTarget},
+    pda,
+    state::{CompetitionEntry, ReferrerStats, UserStake},
+    upline::{LinkAccounts, Upline},
+    PROGRAM_ID,
+};
+use stake_program_tests::{assert_error, staking_error, TestEnv, CONSTRAINT_SEEDS};
+
+const STAKE: u64 = 10_000_000;
+const REWARDS: u64 = 1_000_000_000;
+
+/// Two pools of the same mints. `referrer` and `user` stake in `pool`, `user` linked to `referrer`;
+/// `user` also stakes in `other_pool`, and `referrer` has stats in both.
+struct Fixture {
+    env: TestEnv,
+    pool: PoolKeys,
+    other_pool: PoolKeys,
+    referrer: Keypair,
+    referrer_reward_account: Pubkey,
+    user: Keypair,
+    user_token_account: Pubkey,
+    user_reward_account: Pubkey,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let mut env = TestEnv::start().await;
+        let token_mint = env.create_mint().await;
+        let reward_mint = env.create_mint().await;
+        let pool = env.create_pool(token_mint, reward_mint, REWARDS).await;
+        let other_pool = env.create_pool(token_mint, reward_mint, REWARDS).await;
+
+        let referrer = env.wallet().await;
+        let referrer_token_account = env.token_account(token_mint, referrer.pubkey(), STAKE).await;
+        let referrer_reward_account = env.token_account(reward_mint, referrer.pubkey(), 0).await;
+        stake(&mut env, &pool, &referrer, referrer_token_account, None).await.unwrap();
+        for keys in [&pool, &other_pool] {
+            env.send(&[instruction::initialize_referrer_stats(keys, referrer.pubkey())], &[&referrer]).await.unwrap();
+        }
+
+        let user = env.wallet().await;
+        let user_token_account = env.token_account(token_mint, user.pubkey(), 4 * STAKE).await;
+        let user_reward_account = env.token_account(reward_mint, user.pubkey(), 0).await;
+        stake(&mut env, &pool, &user, user_token_account, Some(referrer.pubkey())).await.unwrap();
+        stake(&mut env, &other_pool, &user, user_token_account, None).await.unwrap();
+
+        Self { env, pool, other_pool, referrer, referrer_reward_account, user, user_token_account, user_reward_account }
+    }
+
+    async fn user_upline(&mut self) -> Upline {
+        let user_stake: UserStake = self.env.account(self.pool.user_stake(&self.user.pubkey())).await;
+        Upline::from_user_stake(&PROGRAM_ID, &user_stake)
+    }
+
+    async fn send_as_user(&mut self, instruction: Instruction) -> Result<(), TransactionError> {
+        let user = self.user.insecure_clone();
+        self.env.send(&[instruction], &[&user]).await
+    }
+}
+
+/// Stakes `STAKE`, resolving the link accounts for a first stake with `referrer`.
+async fn stake(env: &mut TestEnv, keys: &PoolKeys, user: &Keypair, token_account: Pubkey, referrer: Option<Pubkey>) -> Result<(), TransactionError> {
+    let link = match referrer {
+        Some(referrer) => {
+            let referrer_stake: UserStake = env.account(keys.user_stake(&referrer)).await;
+            LinkAccounts::resolve(&PROGRAM_ID, &keys.pool, &referrer, &referrer_stake, None)
+        }
+        None => LinkAccounts::default(),
+    };
+    let instruction = instruction::stake_with_referral(keys, user.pubkey(), Some(token_account), STAKE, referrer, None, &link, None);
+    env.send(&[instruction], &[user]).await
+}
+
+/// `instruction` with the account `from` swapped for `to`.
+fn substitute(mut instruction: Instruction, from: Pubkey, to: Pubkey) -> Instruction {
+    let meta = instruction.accounts.iter_mut().find(|meta| meta.pubkey == from).expect("account to substitute is in the instruction");
+    meta.pubkey = to;
+    instruction
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn another_pools_vault_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, user) = (fx.pool, fx.other_pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+
+    let stake = instruction::stake_with_referral(&pool, user, Some(fx.user_token_account), STAKE, None, None, &LinkAccounts::existing(&upline), None);
+    let result = fx.send_as_user(substitute(stake, pool.staking_vault, other_pool.staking_vault)).await;
+    assert_error(result, staking_error("InvalidStakingVault"));
+
+    let unstake = instruction::unstake(&pool, user, Some(fx.user_token_account), &upline, STAKE);
+    let result = fx.send_as_user(substitute(unstake, pool.staking_vault, other_pool.staking_vault)).await;
+    assert_error(result, staking_error("InvalidStakingVault"));
+
+    let claim = instruction::claim_rewards(&pool, user, fx.user_reward_account, &upline, None);
+    let result = fx.send_as_user(substitute(claim, pool.reward_vault, other_pool.reward_vault)).await;
+    assert_error(result, staking_error("InvalidRewardVault"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn wrong_mint_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, user) = (fx.pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+    let other_mint = fx.env.create_mint().await;
+    let other_mint_account = fx.env.token_account(other_mint, user, STAKE).await;
+    let link = LinkAccounts::existing(&upline);
+
+    let stake = instruction::stake_with_referral(&pool, user, Some(fx.user_token_account), STAKE, None, None, &link, None);
+    let result = fx.send_as_user(substitute(stake, pool.token_mint, other_mint)).await;
+    assert_error(result, staking_error("InvalidMint"));
+
+    let stake = instruction::stake_with_referral(&pool, user, Some(other_mint_account), STAKE, None, None, &link, None);
+    assert_error(fx.send_as_user(stake).await, staking_error("InvalidTokenAccountMint"));
+
+    let claim = instruction::claim_rewards(&pool, user, fx.user_reward_account, &upline, None);
+    let result = fx.send_as_user(substitute(claim, pool.reward_mint, other_mint)).await;
+    assert_error(result, staking_error("InvalidMint"));
+
+    // Rewards paid into an account of the staking mint
+    let claim = instruction::claim_rewards(&pool, user, fx.user_token_account, &upline, None);
+    assert_error(fx.send_as_user(claim).await, staking_error("InvalidTokenAccountMint"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn foreign_user_stake_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, user) = (fx.pool, fx.other_pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+
+    // The user's own stake, but in the other pool
+    let claim = instruction::claim_rewards(&pool, user, fx.user_reward_account, &upline, None);
+    let result = fx.send_as_user(substitute(claim, pool.user_stake(&user), other_pool.user_stake(&user))).await;
+    assert_error(result, staking_error("InvalidUserStakePool"));
+
+    let unstake = instruction::unstake(&pool, user, Some(fx.user_token_account), &upline, STAKE);
+    let result = fx.send_as_user(substitute(unstake, pool.user_stake(&user), other_pool.user_stake(&user))).await;
+    assert_error(result, staking_error("InvalidUserStakePool"));
+
+    // Stakes are created at their PDA, so another pool's can't be topped up through this one
+    let stake = instruction::stake_with_referral(&pool, user, Some(fx.user_token_account), STAKE, None, None, &LinkAccounts::existing(&upline), None);
+    let result = fx.send_as_user(substitute(stake, pool.user_stake(&user), other_pool.user_stake(&user))).await;
+    assert_error(result, CONSTRAINT_SEEDS);
+
+    // Someone else's stake in this pool, claimed or withdrawn by an attacker
+    let attacker = fx.env.wallet().await;
+    let attacker_reward_account = fx.env.token_account(pool.reward_mint, attacker.pubkey(), 0).await;
+    let attacker_token_account = fx.env.token_account(pool.token_mint, attacker.pubkey(), 0).await;
+
+    let claim = instruction::claim_rewards(&pool, attacker.pubkey(), attacker_reward_account, &upline, None);
+    let claim = substitute(claim, pool.user_stake(&attacker.pubkey()), pool.user_stake(&user));
+    assert_error(fx.env.send(&[claim], &[&attacker]).await, staking_error("InvalidUserStakeOwner"));
+
+    let unstake = instruction::unstake(&pool, attacker.pubkey(), Some(attacker_token_account), &upline, STAKE);
+    let unstake = substitute(unstake, pool.user_stake(&attacker.pubkey()), pool.user_stake(&user));
+    assert_error(fx.env.send(&[unstake], &[&attacker]).await, staking_error("InvalidUserStakeOwner"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn foreign_referrer_stats_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, referrer) = (fx.pool, fx.other_pool, fx.referrer.pubkey());
+    let own_stats = pool.referrer_stats(&referrer);
+    let foreign_stats = other_pool.referrer_stats(&referrer);
+
+    // The referrer's stats from the other pool, claimed against this pool's vault
+    let claim = instruction::claim_referral_rewards(&pool, referrer, fx.referrer_reward_account);
+    let claim = substitute(claim, own_stats, foreign_stats);
+    let signer = fx.referrer.insecure_clone();
+    assert_error(fx.env.send(&[claim], &[&signer]).await, CONSTRAINT_SEEDS);
+
+    // Non-strict: a top-up with the other pool's stats as L1 goes through but credits nobody
+    let own_before: ReferrerStats = fx.env.account(own_stats).await;
+    let foreign_before: ReferrerStats = fx.env.account(foreign_stats).await;
+    let link = LinkAccounts { referrer_stats: Some(foreign_stats), ..LinkAccounts::default() };
+    let top_up = instruction::stake_with_referral(&pool, fx.user.pubkey(), Some(fx.user_token_account), STAKE, None, None, &link, None);
+    fx.send_as_user(top_up.clone()).await.unwrap();
+    assert_eq!(fx.env.account::<ReferrerStats>(own_stats).await, own_before);
+    assert_eq!(fx.env.account::<ReferrerStats>(foreign_stats).await, foreign_before);
+
+    // Strict: the same top-up is refused
+    let authority = fx.env.authority.insecure_clone();
+    fx.env.send(&[instruction::set_strict_referrer_accounts(&pool, true)], &[&authority]).await.unwrap();
+    assert_error(fx.send_as_user(top_up).await, staking_error("InvalidReferrerAccount"));
+
+    // ...but an unstake without the upline's stats still goes through, leaving them as they were
+    fx.env.send(&[instruction::update_lock_duration(&pool, 0)], &[&authority]).await.unwrap();
+    let unstake = instruction::unstake(&pool, fx.user.pubkey(), Some(fx.user_token_account), &Upline::default(), STAKE);
+    fx.send_as_user(unstake).await.unwrap();
+    assert_eq!(fx.env.account::<ReferrerStats>(own_stats).await, own_before);
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn foreign_fee_receiver_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, user) = (fx.pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+    let attacker = Pubkey::new_unique();
+
+    let stake = instruction::stake_with_referral(&pool, user, Some(fx.user_token_account), STAKE, None, None, &LinkAccounts::existing(&upline), None);
+    let result = fx.send_as_user(substitute(stake, pool.fee_receiver, attacker)).await;
+    assert_error(result, staking_error("InvalidFeeReceiver"));
+
+    let claim = instruction::claim_rewards(&pool, user, fx.user_reward_account, &upline, None);
+    let result = fx.send_as_user(substitute(claim, pool.fee_receiver, attacker)).await;
+    assert_error(result, staking_error("InvalidFeeReceiver"));
+
+    let unstake = instruction::unstake(&pool, user, Some(fx.user_token_account), &upline, STAKE);
+    let result = fx.send_as_user(substitute(unstake, pool.fee_receiver, attacker)).await;
+    assert_error(result, staking_error("InvalidFeeReceiver"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn restaking_into_the_source_pool_as_another_pool_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, user) = (fx.pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+
+    let authority = fx.env.authority.insecure_clone();
+    let result = fx.env.send(&[instruction::set_restake_pool(&pool, pool.pool)], &[&authority]).await;
+    assert_error(result, staking_error("InvalidRestakeTarget"));
+
+    // The source pool and stake passed again as the target would be mutated twice
+    let target = RestakeTarget::OtherPool { pool: pool.pool, staking_vault: pool.staking_vault, upline };
+    let claim = instruction::claim_rewards(&pool, user, fx.user_reward_account, &upline, Some(target));
+    assert_error(fx.send_as_user(claim).await, staking_error("InvalidRestakeTarget"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn admin_transfers_with_another_pools_vault_are_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool) = (fx.pool, fx.other_pool);
+    let authority = fx.env.authority.insecure_clone();
+    let funder_account = fx.env.token_account(pool.reward_mint, authority.pubkey(), STAKE).await;
+    let admin_token_account = fx.env.token_account(pool.token_mint, authority.pubkey(), 0).await;
+
+    let add = instruction::add_rewards(&pool, authority.pubkey(), funder_account, STAKE);
+    let result = fx.env.send(&[substitute(add, pool.reward_vault, other_pool.reward_vault)], &[&authority]).await;
+    assert_error(result, staking_error("InvalidRewardVault"));
+
+    let withdraw = instruction::withdraw_tokens(&pool, admin_token_account, 1);
+    let result = fx.env.send(&[substitute(withdraw.clone(), pool.staking_vault, other_pool.staking_vault)], &[&authority]).await;
+    assert_error(result, staking_error("InvalidStakingVault"));
+
+    // Anyone but the pool's authority
+    let attacker = fx.env.wallet().await;
+    let result = fx.env.send(&[substitute(withdraw.clone(), pool.authority, attacker.pubkey())], &[&attacker]).await;
+    assert_error(result, staking_error("Unauthorized"));
+
+    // With the right accounts the vault holds nothing but staked principal, so there's no surplus to take
+    let result = fx.env.send(&[withdraw], &[&authority]).await;
+    assert_error(result, staking_error("WithdrawExceedsSurplus"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn claim_all_with_foreign_accounts_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, user) = (fx.pool, fx.other_pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+
+    let claim = instruction::claim_all(&pool, user, fx.user_reward_account, &upline, false);
+    let result = fx.send_as_user(substitute(claim.clone(), pool.user_stake(&user), other_pool.user_stake(&user))).await;
+    assert_error(result, CONSTRAINT_SEEDS);
+    let result = fx.send_as_user(substitute(claim, pool.reward_vault, other_pool.reward_vault)).await;
+    assert_error(result, staking_error("InvalidRewardVault"));
+
+    // The referrer's own commission, taken from their stats in the other pool
+    let referrer = fx.referrer.insecure_clone();
+    let claim = instruction::claim_all(&pool, referrer.pubkey(), fx.referrer_reward_account, &Upline::default(), true);
+    let claim = substitute(claim, pool.referrer_stats(&referrer.pubkey()), other_pool.referrer_stats(&referrer.pubkey()));
+    assert_error(fx.env.send(&[claim], &[&referrer]).await, CONSTRAINT_SEEDS);
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn emergency_withdraw_with_foreign_accounts_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, user) = (fx.pool, fx.other_pool, fx.user.pubkey());
+    let upline = fx.user_upline().await;
+    let authority = fx.env.authority.insecure_clone();
+    fx.env.send(&[instruction::set_emergency_mode(&pool, true)], &[&authority]).await.unwrap();
+
+    let withdraw = instruction::emergency_withdraw(&pool, user, Some(fx.user_token_account), &upline);
+    let result = fx.send_as_user(substitute(withdraw.clone(), pool.user_stake(&user), other_pool.user_stake(&user))).await;
+    assert_error(result, CONSTRAINT_SEEDS);
+    let result = fx.send_as_user(substitute(withdraw, pool.staking_vault, other_pool.staking_vault)).await;
+    assert_error(result, staking_error("InvalidStakingVault"));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn vesting_with_foreign_accounts_is_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, referrer) = (fx.pool, fx.other_pool, fx.referrer.pubkey());
+    let authority = fx.env.authority.insecure_clone();
+    let signer = fx.referrer.insecure_clone();
+    fx.env.send(&[instruction::update_commission_vesting(&pool, 0, 1_000)], &[&authority]).await.unwrap();
+
+    // The user's claim credits the referrer commission in `pool`
+    fx.env.advance_clock(1_000).await;
+    let upline = fx.user_upline().await;
+    fx.send_as_user(instruction::claim_rewards(&pool, fx.user.pubkey(), fx.user_reward_account, &upline, None)).await.unwrap();
+
+    // Vesting it against the referrer's stats from the other pool
+    let vest = instruction::vest_referral_rewards(&pool, referrer, 0);
+    let result = fx.env.send(&[substitute(vest.clone(), pool.referrer_stats(&referrer), other_pool.referrer_stats(&referrer))], &[&signer]).await;
+    assert_error(result, CONSTRAINT_SEEDS);
+
+    fx.env.send(&[vest], &[&signer]).await.unwrap();
+    let grant = pda::commission_grant(&PROGRAM_ID, &pool.pool, &referrer, 0).0;
+    fx.env.advance_clock(1_000).await;
+    let release = instruction::release_vested_commission(&pool, referrer, grant, fx.referrer_reward_account);
+    let result = fx.env.send(&[substitute(release, pool.reward_vault, other_pool.reward_vault)], &[&signer]).await;
+    assert_error(result, staking_error("InvalidRewardVault"));
+
+    // The grant paid out of the other pool
+    let release = instruction::release_vested_commission(&other_pool, referrer, grant, fx.referrer_reward_account);
+    assert_error(fx.env.send(&[release], &[&signer]).await, staking_error("InvalidCommissionGrant"));
+
+    // Paid out in full, the grant is closed and no longer holds the stats open
+    fx.env.send(&[instruction::release_vested_commission(&pool, referrer, grant, fx.referrer_reward_account)], &[&signer]).await.unwrap();
+    assert!(fx.env.raw_account(grant).await.is_none());
+    let stats: ReferrerStats = fx.env.account(pool.referrer_stats(&referrer)).await;
+    assert_eq!((stats.grant_count, stats.open_grants), (1, 0));
+}
+
+#[tokio::test]
+#[ignore = "needs the built program; see the crate docs"]
+async fn competition_accounts_of_another_pool_are_rejected() {
+    let mut fx = Fixture::new().await;
+    let (pool, other_pool, referrer) = (fx.pool, fx.other_pool, fx.referrer.pubkey());
+    let authority = fx.env.authority.insecure_clone();
+    let signer = fx.referrer.insecure_clone();
+    let now = fx.env.now().await;
+    for keys in [&pool, &other_pool] {
+        fx.env.send(&[instruction::create_competition(keys, 0, now, now + 86_400, vec![10_000])], &[&authority]).await.unwrap();
+    }
+    let competition = pda::referral_competition(&PROGRAM_ID, &pool.pool, 0).0;
+    let other_competition = pda::referral_competition(&PROGRAM_ID, &other_pool.pool, 0).0;
+    let other_entry = pda::competition_entry(&PROGRAM_ID, &other_competition, &referrer).0;
+    fx.env.send(&[instruction::enter_competition(PROGRAM_ID, other_competition, referrer)], &[&signer]).await.unwrap();
+
+    // A referred top-up credited to the other pool's round
+    let upline = fx.user_upline().await;
+    let link = LinkAccounts::existing(&upline);
+    let stake = instruction::stake_with_referral(&pool, fx.user.pubkey(), Some(fx.user_token_account), STAKE, None, None, &link, Some((other_competition, other_entry)));
+    assert_error(fx.send_as_user(stake).await, staking_error("InvalidCompetition"));
+
+    // Leaving the active round's accounts off, so the volume never reaches it
+    let stake = instruction::stake_with_referral(&pool, fx.user.pubkey(), Some(fx.user_token_account), STAKE, None, None, &link, None);
+    assert_error(fx.send_as_user(stake).await, staking_error("CompetitionAccountsRequired"));
+
+    // Only one unsettled round per pool
+    let result = fx.env.send(&[instruction::create_competition(&pool, 1, now, now + 86_400, vec![10_000])], &[&authority]).await;
+    assert_error(result, staking_error("CompetitionAlreadyActive"));
+
+    // With the right round, volume is credited once the referrer has entered
+    let entry = pda::competition_entry(&PROGRAM_ID, &competition, &referrer).0;
+    fx.env.send(&[instruction::enter_competition(PROGRAM_ID, competition, referrer)], &[&signer]).await.unwrap();
+    let stake = instruction::stake_with_referral(&pool, fx.user.pubkey(), Some(fx.user_token_account), STAKE, None, None, &link, Some((competition, entry)));
+    fx.send_as_user(stake).await.unwrap();
+    assert_eq!(fx.env.account::<CompetitionEntry>(entry).await.volume, STAKE);
+
+    // Settling the other pool's round through this pool
+    let settle = instruction::settle_competition(&other_pool, other_competition, &[]);
+    let result = fx.env.send(&[substitute(settle, other_pool.pool, pool.pool)], &[]).await;
+    assert_error(result, staking_error("InvalidCompetition"));
+
+    // Funding one round's prize into another round's vault
+    let funder_account = fx.env.token_account(pool.reward_mint, authority.pubkey(), STAKE).await;
+    let prize_vault = pda::associated_token_account(&competition, &pool.reward_mint, &pool.reward_token_program);
+    let other_prize_vault = pda::associated_token_account(&other_competition, &pool.reward_mint, &pool.reward_token_program);
+    let fund = instruction::fund_competition(&pool, competition, authority.pubkey(), funder_account, STAKE);
+    let result = fx.env.send(&[substitute(fund, prize_vault, other_prize_vault)], &[&authority]).await;
+    assert_error(result, staking_error("InvalidPrizeVault"));
+
+    // Sweeping the other pool's round through this pool
+    let sweep = instruction::sweep_competition_remainder(&pool, other_competition, funder_account);
+    assert_error(fx.env.send(&[sweep], &[&authority]).await, staking_error("InvalidCompetition"));
+}
@@ -74,6 +74,7 @@ export interface AppConfig {
   programId: string;
   tokenAddress: string;
   rewardTokenAddress: string;
+  poolId: number;
   adminAddress: string;
   network: 'devnet' | 'mainnet-beta' | 'testnet';
   tokenName: string;
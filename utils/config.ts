@@ -6,6 +6,7 @@ export const getConfig = (): AppConfig => {
     programId: (process.env.NEXT_PUBLIC_PROGRAM_ID || '5YNT6aKnnaDsUC7PYw3AJoe5GSjK9Gc76Ro79EMMWVSY').trim(),
     tokenAddress: (process.env.NEXT_PUBLIC_TOKEN_ADDRESS || 'GEF1NPkok8EpZQP2D9Qxxf5ULiSUHTJDz8X9gtG4PrvV').trim(),
     rewardTokenAddress: (process.env.NEXT_PUBLIC_REWARD_TOKEN_ADDRESS || process.env.NEXT_PUBLIC_TOKEN_ADDRESS || 'GEF1NPkok8EpZQP2D9Qxxf5ULiSUHTJDz8X9gtG4PrvV').trim(),
+    poolId: Number(process.env.NEXT_PUBLIC_POOL_ID || 0),
     adminAddress: (process.env.NEXT_PUBLIC_ADMIN || '').trim(),
     network: (process.env.NEXT_PUBLIC_ACTIVE_NETWORK as 'devnet' | 'mainnet-beta' | 'testnet') || 'devnet',
 
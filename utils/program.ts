@@ -1,13 +1,15 @@
 import * as anchor from '@coral-xyz/anchor';
 import { Program, AnchorProvider } from '@coral-xyz/anchor';
 import { Connection, PublicKey, SystemProgram, Transaction, SYSVAR_RENT_PUBKEY } from '@solana/web3.js';
-import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createAssociatedTokenAccountInstruction, getAccount } from '@solana/spl-token';
+import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createAssociatedTokenAccountInstruction, getAccount } from '@solana/spl-token';
 import { getConfig } from './config';
 import { confirmTransactionWithPolling } from './connection';
 import IDL from '@/lib/idl.json';
 import toast from 'react-hot-toast';
 import { AnchorWallet } from '@/types';
 
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
+
 export const getProgram = (connection: Connection, wallet: AnchorWallet): Program => {
   const provider = new AnchorProvider(
     connection,
@@ -20,39 +22,73 @@ export const getProgram = (connection: Connection, wallet: AnchorWallet): Progra
   return new Program(IDL as any, programId, provider);
 };
 
+export const getPoolRegistryPDA = (program: Program): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync([Buffer.from('pool_registry')], program.programId);
+};
+
+export const getStakingPoolPDA = (
+  program: Program,
+  tokenMint: PublicKey,
+  poolId: number
+): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('staking_pool'), tokenMint.toBuffer(), new anchor.BN(poolId).toArrayLike(Buffer, 'le', 8)],
+    program.programId
+  );
+};
+
+export const getPoolEntryPDA = (program: Program, poolId: number): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('pool_entry'), new anchor.BN(poolId).toArrayLike(Buffer, 'le', 8)],
+    program.programId
+  );
+};
+
+// Vaults are PDAs of the pool, not ATAs
+export const getVaultPDAs = (program: Program, stakingPool: PublicKey) => {
+  const [stakingVault] = PublicKey.findProgramAddressSync(
+    [Buffer.from('staking_vault'), stakingPool.toBuffer()],
+    program.programId
+  );
+  const [rewardVault] = PublicKey.findProgramAddressSync(
+    [Buffer.from('reward_vault'), stakingPool.toBuffer()],
+    program.programId
+  );
+  return { stakingVault, rewardVault };
+};
+
+// SPL Token or Token-2022, whichever owns the mint; falls back to SPL Token if it can't be read
+const getTokenPrograms = async (connection: Connection, tokenMint: PublicKey, rewardMint: PublicKey) => {
+  const [tokenMintInfo, rewardMintInfo] = await connection.getMultipleAccountsInfo([tokenMint, rewardMint]);
+  return {
+    stakingTokenProgram: tokenMintInfo?.owner ?? TOKEN_PROGRAM_ID,
+    rewardTokenProgram: rewardMintInfo?.owner ?? TOKEN_PROGRAM_ID,
+  };
+};
+
 export const getProgramAccounts = async (
   program: Program,
   tokenMintAddress: string,
-  rewardMintAddress?: string
+  rewardMintAddress?: string,
+  poolId: number = getConfig().poolId
 ) => {
   const config = getConfig();
   const tokenMint = new PublicKey(tokenMintAddress);
   const rewardMint = new PublicKey(rewardMintAddress || config.rewardTokenAddress);
 
-  const [stakingPool] = PublicKey.findProgramAddressSync(
-    [Buffer.from('staking_pool'), tokenMint.toBuffer()],
-    program.programId
-  );
-
-  // Use Associated Token Addresses (ATAs) for vaults, not PDAs
-  const stakingVault = await getAssociatedTokenAddress(
-    tokenMint,
-    stakingPool,
-    true // allowOwnerOffCurve
-  );
-
-  const rewardVault = await getAssociatedTokenAddress(
-    rewardMint,
-    stakingPool,
-    true // allowOwnerOffCurve
-  );
+  const [stakingPool] = getStakingPoolPDA(program, tokenMint, poolId);
+  const { stakingVault, rewardVault } = getVaultPDAs(program, stakingPool);
+  const { stakingTokenProgram, rewardTokenProgram } = await getTokenPrograms(program.provider.connection, tokenMint, rewardMint);
 
   return {
+    poolId,
     stakingPool,
     stakingVault,
     rewardVault,
     tokenMint,
     rewardMint,
+    stakingTokenProgram,
+    rewardTokenProgram,
   };
 };
 
@@ -69,15 +105,25 @@ export const getUserStakePDA = (
 
 export const getReferrerStatsPDA = (
   program: Program,
-  referrer: PublicKey
+  referrer: PublicKey,
+  stakingPool: PublicKey
 ): [PublicKey, number] => {
   return PublicKey.findProgramAddressSync(
-    [Buffer.from('referrer_stats'), referrer.toBuffer()],
+    [Buffer.from('referrer_stats'), stakingPool.toBuffer(), referrer.toBuffer()],
     program.programId
   );
 };
 
-// ... (previous imports)
+export const getCompetitionEntryPDA = (
+  program: Program,
+  competition: PublicKey,
+  referrer: PublicKey
+): [PublicKey, number] => {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('competition_entry'), competition.toBuffer(), referrer.toBuffer()],
+    program.programId
+  );
+};
 
 export const initializePool = async (
   program: Program,
@@ -85,39 +131,63 @@ export const initializePool = async (
   tokenMintAddress: string,
   rewardMintAddress: string,
   rewardRate: anchor.BN,
-  enableReferral: boolean
+  enableReferral: boolean,
+  name: string = getConfig().tokenName,
+  startTime: anchor.BN = new anchor.BN(0),
+  endTime: anchor.BN = new anchor.BN(0) // 0 = open-ended
 ) => {
   const connection = program.provider.connection;
-  const tokenMint = new PublicKey(tokenMintAddress);
-  const rewardMint = new PublicKey(rewardMintAddress);
-
-  const [stakingPool] = PublicKey.findProgramAddressSync(
-    [Buffer.from('staking_pool'), tokenMint.toBuffer()],
-    program.programId
-  );
+  const [registry] = getPoolRegistryPDA(program);
 
-  const stakingVault = await getAssociatedTokenAddress(tokenMint, stakingPool, true);
-  const rewardVault = await getAssociatedTokenAddress(rewardMint, stakingPool, true);
-
-  // ... (token account creation logic same as before) ...
+  try {
+    const tx = new Transaction();
 
+    // The new pool takes the registry's next id; the registry itself is created on first use,
+    // which only the program's upgrade authority may do
+    let poolId = 0;
+    const registryData = await program.account.poolRegistry.fetchNullable(registry);
+    if (registryData) {
+      poolId = (registryData.poolCount as anchor.BN).toNumber();
+    } else {
+      const [programData] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+      );
+      tx.add(
+        await program.methods
+          .initializeRegistry()
+          .accounts({
+            registry,
+            authority: wallet.publicKey,
+            program: program.programId,
+            programData,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction()
+      );
+    }
 
-  try {
-    const tx = await program.methods
-      .initialize(rewardRate, enableReferral)
-      .accounts({
-        stakingPool,
-        tokenMint,
-        rewardMint,
-        stakingVault,
-        rewardVault,
-        authority: wallet.publicKey,
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-        rent: SYSVAR_RENT_PUBKEY,
-      })
-      .transaction();
+    const accounts = await getProgramAccounts(program, tokenMintAddress, rewardMintAddress, poolId);
+    const [poolEntry] = getPoolEntryPDA(program, poolId);
+    tx.add(
+      await program.methods
+        .initialize(rewardRate, enableReferral, name, startTime, endTime)
+        .accounts({
+          registry,
+          stakingPool: accounts.stakingPool,
+          poolEntry,
+          tokenMint: accounts.tokenMint,
+          rewardMint: accounts.rewardMint,
+          stakingVault: accounts.stakingVault,
+          rewardVault: accounts.rewardVault,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+          stakingTokenProgram: accounts.stakingTokenProgram,
+          rewardTokenProgram: accounts.rewardTokenProgram,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .instruction()
+    );
 
     const latestBlockhash = await connection.getLatestBlockhash();
     tx.recentBlockhash = latestBlockhash.blockhash;
@@ -142,7 +212,7 @@ export const initializeReferrerStats = async (
 ) => {
   const connection = program.provider.connection;
   const accounts = await getProgramAccounts(program, tokenMintAddress);
-  const [referrerStats] = getReferrerStatsPDA(program, wallet.publicKey);
+  const [referrerStats] = getReferrerStatsPDA(program, wallet.publicKey, accounts.stakingPool);
 
   const config = getConfig();
   const feeReceiver = new PublicKey(config.adminAddress);
@@ -162,6 +232,7 @@ export const initializeReferrerStats = async (
     const ix = await program.methods
       .initializeReferrerStats()
       .accounts({
+        stakingPool: accounts.stakingPool,
         referrerStats,
         referrer: wallet.publicKey,
         systemProgram: SystemProgram.programId,
@@ -193,12 +264,13 @@ export const stakeWithReferral = async (
   wallet: AnchorWallet,
   tokenMintAddress: string,
   amount: anchor.BN,
-  referrer: PublicKey | null
+  referrer: PublicKey | null,
+  allowlistProof: { cap: anchor.BN; proof: number[][] } | null = null
 ) => {
   const connection = program.provider.connection;
   const accounts = await getProgramAccounts(program, tokenMintAddress);
   const [userStake] = getUserStakePDA(program, wallet.publicKey, accounts.stakingPool);
-  const userTokenAccount = await getAssociatedTokenAddress(accounts.tokenMint, wallet.publicKey);
+  const userTokenAccount = await getAssociatedTokenAddress(accounts.tokenMint, wallet.publicKey, false, accounts.stakingTokenProgram);
 
   // Get fee receiver from config (admin address)
   const config = getConfig();
@@ -229,14 +301,13 @@ export const stakeWithReferral = async (
     }
 
     if (effectiveReferrer) {
-      // Get L1 Stats
-      const [pda] = getReferrerStatsPDA(program, effectiveReferrer);
+      // Get L1 Stats (the program requires them, and L1's stake, on a new link)
+      const [pda] = getReferrerStatsPDA(program, effectiveReferrer, accounts.stakingPool);
       referrerStats = pda;
 
       // Get L1 User Stake (to find L2)
       try {
-        const programAccounts = await getProgramAccounts(program, tokenMintAddress);
-        const [l1Stake] = getUserStakePDA(program, effectiveReferrer, programAccounts.stakingPool);
+        const [l1Stake] = getUserStakePDA(program, effectiveReferrer, accounts.stakingPool);
         referrerUserStake = l1Stake;
 
         // Fetch L1 Stake Data to see if they have a referrer (L2)
@@ -245,11 +316,11 @@ export const stakeWithReferral = async (
           const l2Key = l1StakeData.referrer as unknown as PublicKey;
 
           // Get L2 Stats
-          const [l2StatsPDA] = getReferrerStatsPDA(program, l2Key);
+          const [l2StatsPDA] = getReferrerStatsPDA(program, l2Key, accounts.stakingPool);
           referrerStatsL2 = l2StatsPDA;
 
           // Get L2 User Stake (to find L3)
-          const [l2StakePDA] = getUserStakePDA(program, l2Key, programAccounts.stakingPool);
+          const [l2StakePDA] = getUserStakePDA(program, l2Key, accounts.stakingPool);
           l2UserStake = l2StakePDA;
 
           // Fetch L2 Stake Data to see if they have a referrer (L3)
@@ -257,7 +328,7 @@ export const stakeWithReferral = async (
             const l2StakeData = await program.account.userStake.fetch(l2StakePDA);
             if (l2StakeData.referrer && l2StakeData.referrer.toString() !== PublicKey.default.toString()) {
               const l3Key = l2StakeData.referrer as unknown as PublicKey;
-              const [l3StatsPDA] = getReferrerStatsPDA(program, l3Key);
+              const [l3StatsPDA] = getReferrerStatsPDA(program, l3Key, accounts.stakingPool);
               referrerStatsL3 = l3StatsPDA;
             }
           } catch (e) {
@@ -272,12 +343,22 @@ export const stakeWithReferral = async (
     // Hardcoded Frontend Fee: 0.001 SOL
     const feeAmount = 1_000_000;
     let currentFeeReceiver = feeReceiverAccount;
+    // The pool's unsettled competition round (and L1's entry in it) must be passed while there is one
+    let competition: PublicKey | null = null;
+    let competitionEntry: PublicKey | null = null;
 
     try {
       const poolData = await program.account.stakingPool.fetch(accounts.stakingPool);
       if (poolData.feeReceiver && poolData.feeReceiver.toString() !== PublicKey.default.toString()) {
         currentFeeReceiver = poolData.feeReceiver as PublicKey;
       }
+      const activeCompetition = poolData.activeCompetition as PublicKey;
+      if (!activeCompetition.equals(PublicKey.default)) {
+        competition = activeCompetition;
+        if (effectiveReferrer) {
+          [competitionEntry] = getCompetitionEntryPDA(program, competition, effectiveReferrer);
+        }
+      }
     } catch (e) {
       console.warn("Used default fee receiver due to fetch error:", e);
     }
@@ -296,20 +377,23 @@ export const stakeWithReferral = async (
     }
 
     const stakeIx = await program.methods
-      .stakeWithReferral(amount, referrer)
+      .stakeWithReferral(amount, referrer, allowlistProof)
       .accounts({
         stakingPool: accounts.stakingPool,
         userStake,
         stakingVault: accounts.stakingVault,
         userTokenAccount,
-        feeReceiverAccount,
+        tokenMint: accounts.tokenMint,
+        feeReceiverAccount: currentFeeReceiver,
         referrerStats: referrerStats as any,
         referrerUserStake: referrerUserStake as any,
         referrerStatsL2: referrerStatsL2 as any,
         l2UserStake: l2UserStake as any,
         referrerStatsL3: referrerStatsL3 as any,
+        competition: competition as any,
+        competitionEntry: competitionEntry as any,
         user: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram: accounts.stakingTokenProgram,
         systemProgram: SystemProgram.programId,
       })
       .instruction();
@@ -346,7 +430,7 @@ export const claimRewards = async (
   const accounts = await getProgramAccounts(program, tokenMintAddress, rewardMintAddress);
   const [userStake] = getUserStakePDA(program, wallet.publicKey, accounts.stakingPool);
   const rewardMint = new PublicKey(rewardMintAddress);
-  const userRewardAccount = await getAssociatedTokenAddress(rewardMint, wallet.publicKey);
+  const userRewardAccount = await getAssociatedTokenAddress(rewardMint, wallet.publicKey, false, accounts.rewardTokenProgram);
 
   // Get fee receiver from config (admin address)
   const config = getConfig();
@@ -364,25 +448,25 @@ export const claimRewards = async (
     // L1 - Direct referrer from the user's stake data
     const l1Referrer = userStakeData.referrer as PublicKey | null;
     if (l1Referrer && l1Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStatsL1] = getReferrerStatsPDA(program, l1Referrer);
+      [referrerStatsL1] = getReferrerStatsPDA(program, l1Referrer, accounts.stakingPool);
     }
 
     // L2 - Referrer's referrer (stored in referrerL2 field)
     const l2Referrer = (userStakeData as any).referrerL2 as PublicKey | null;
     if (l2Referrer && l2Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStatsL2] = getReferrerStatsPDA(program, l2Referrer);
+      [referrerStatsL2] = getReferrerStatsPDA(program, l2Referrer, accounts.stakingPool);
     }
 
     // L3 - L2's referrer (stored in referrerL3 field)
     const l3Referrer = (userStakeData as any).referrerL3 as PublicKey | null;
     if (l3Referrer && l3Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStatsL3] = getReferrerStatsPDA(program, l3Referrer);
+      [referrerStatsL3] = getReferrerStatsPDA(program, l3Referrer, accounts.stakingPool);
     }
   } catch (e) {
     console.log("Could not fetch user stake data for referrer chain:", e);
     // Fall back to using the provided referrer for L1 only
     if (referrer) {
-      [referrerStatsL1] = getReferrerStatsPDA(program, referrer);
+      [referrerStatsL1] = getReferrerStatsPDA(program, referrer, accounts.stakingPool);
     }
   }
 
@@ -413,19 +497,28 @@ export const claimRewards = async (
       );
     }
 
+    // Paid out to the wallet; the restake accounts are only needed for the restake destinations
     const claimIx = await program.methods
-      .claimRewards()
+      .claimRewards({ wallet: {} })
       .accounts({
         stakingPool: accounts.stakingPool,
         userStake,
         rewardVault: accounts.rewardVault,
         userRewardAccount,
-        feeReceiverAccount,
+        rewardMint,
+        feeReceiverAccount: currentFeeReceiver,
         referrerStatsL1: referrerStatsL1 as any, // Cast to any for optional account
         referrerStatsL2: referrerStatsL2 as any,
         referrerStatsL3: referrerStatsL3 as any,
+        stakingVault: null as any,
+        targetPool: null as any,
+        targetUserStake: null as any,
+        targetStakingVault: null as any,
+        targetReferrerStatsL1: null as any,
+        targetReferrerStatsL2: null as any,
+        targetReferrerStatsL3: null as any,
         user: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram: accounts.rewardTokenProgram,
         systemProgram: SystemProgram.programId,
       })
       .instruction();
@@ -460,7 +553,7 @@ export const unstake = async (
   const connection = program.provider.connection;
   const accounts = await getProgramAccounts(program, tokenMintAddress);
   const [userStake] = getUserStakePDA(program, wallet.publicKey, accounts.stakingPool);
-  const userTokenAccount = await getAssociatedTokenAddress(accounts.tokenMint, wallet.publicKey);
+  const userTokenAccount = await getAssociatedTokenAddress(accounts.tokenMint, wallet.publicKey, false, accounts.stakingTokenProgram);
 
   // Get fee receiver from config (admin address)
   const config = getConfig();
@@ -477,19 +570,19 @@ export const unstake = async (
     // L1
     const l1Referrer = userStakeData.referrer as PublicKey | null;
     if (l1Referrer && l1Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStats] = getReferrerStatsPDA(program, l1Referrer);
+      [referrerStats] = getReferrerStatsPDA(program, l1Referrer, accounts.stakingPool);
     }
 
     // L2
     const l2Referrer = (userStakeData as any).referrerL2 as PublicKey | null;
     if (l2Referrer && l2Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStatsL2] = getReferrerStatsPDA(program, l2Referrer);
+      [referrerStatsL2] = getReferrerStatsPDA(program, l2Referrer, accounts.stakingPool);
     }
 
     // L3
     const l3Referrer = (userStakeData as any).referrerL3 as PublicKey | null;
     if (l3Referrer && l3Referrer.toString() !== PublicKey.default.toString()) {
-      [referrerStatsL3] = getReferrerStatsPDA(program, l3Referrer);
+      [referrerStatsL3] = getReferrerStatsPDA(program, l3Referrer, accounts.stakingPool);
     }
   } catch (e) {
     console.log("Could not fetch user stake for referrers:", e);
@@ -529,12 +622,14 @@ export const unstake = async (
         userStake,
         stakingVault: accounts.stakingVault,
         userTokenAccount,
-        feeReceiverAccount,
+        nativeUnwrapAccount: null as any, // Only used by native SOL pools paying out to the wallet
+        tokenMint: accounts.tokenMint,
+        feeReceiverAccount: currentFeeReceiver,
         referrerStats: referrerStats as any,
         referrerStatsL2: referrerStatsL2 as any,
         referrerStatsL3: referrerStatsL3 as any,
         user: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram: accounts.stakingTokenProgram,
         systemProgram: SystemProgram.programId,
       })
       .instruction();
@@ -567,9 +662,9 @@ export const claimReferralRewards = async (
 ) => {
   const connection = program.provider.connection;
   const accounts = await getProgramAccounts(program, tokenMintAddress, rewardMintAddress);
-  const [referrerStats] = getReferrerStatsPDA(program, wallet.publicKey);
+  const [referrerStats] = getReferrerStatsPDA(program, wallet.publicKey, accounts.stakingPool);
   const rewardMint = new PublicKey(rewardMintAddress);
-  const referrerRewardAccount = await getAssociatedTokenAddress(rewardMint, wallet.publicKey);
+  const referrerRewardAccount = await getAssociatedTokenAddress(rewardMint, wallet.publicKey, false, accounts.rewardTokenProgram);
 
   // Get fee receiver from config (admin address)
   const config = getConfig();
@@ -610,8 +705,9 @@ export const claimReferralRewards = async (
         referrerStats,
         rewardVault: accounts.rewardVault,
         referrerRewardAccount,
+        rewardMint,
         referrer: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID
+        tokenProgram: accounts.rewardTokenProgram
       })
       .instruction();
 
@@ -645,11 +741,13 @@ export const addRewards = async (
   amount: anchor.BN
 ) => {
   const connection = program.provider.connection;
-  const accounts = await getProgramAccounts(program, tokenMintAddress);
+  const accounts = await getProgramAccounts(program, tokenMintAddress, rewardMintAddress);
   const rewardMint = new PublicKey(rewardMintAddress);
   const funderRewardAccount = await getAssociatedTokenAddress(
     rewardMint,
-    wallet.publicKey
+    wallet.publicKey,
+    false,
+    accounts.rewardTokenProgram
   );
 
 
@@ -660,8 +758,9 @@ export const addRewards = async (
         stakingPool: accounts.stakingPool,
         rewardVault: accounts.rewardVault,
         funderRewardAccount,
+        rewardMint,
         funder: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram: accounts.rewardTokenProgram,
       })
       .transaction();
 
@@ -783,13 +882,15 @@ export const setPauseState = async (
 ) => {
   const connection = program.provider.connection;
   const accounts = await getProgramAccounts(program, tokenMintAddress);
-
+  // The registry entry's status follows the pause state
+  const [poolEntry] = getPoolEntryPDA(program, accounts.poolId);
 
   try {
     const tx = await program.methods
       .setPauseState(paused)
       .accounts({
         stakingPool: accounts.stakingPool,
+        poolEntry,
         authority: wallet.publicKey,
       })
       .transaction();
@@ -828,7 +929,9 @@ export const withdrawTokens = async (
   const accounts = await getProgramAccounts(program, tokenMintAddress);
   const adminTokenAccount = await getAssociatedTokenAddress(
     accounts.tokenMint,
-    wallet.publicKey
+    wallet.publicKey,
+    false,
+    accounts.stakingTokenProgram
   );
 
 
@@ -839,8 +942,9 @@ export const withdrawTokens = async (
         stakingPool: accounts.stakingPool,
         stakingVault: accounts.stakingVault,
         adminTokenAccount,
+        tokenMint: accounts.tokenMint,
         authority: wallet.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram: accounts.stakingTokenProgram,
       })
       .transaction();
 
@@ -947,9 +1051,11 @@ export const fetchUserStake = async (
 
 export const fetchReferrerStats = async (
   program: Program,
-  referrer: PublicKey
+  referrer: PublicKey,
+  tokenMintAddress: string = getConfig().tokenAddress
 ) => {
-  const [referrerStats] = getReferrerStatsPDA(program, referrer);
+  const [stakingPool] = getStakingPoolPDA(program, new PublicKey(tokenMintAddress), getConfig().poolId);
+  const [referrerStats] = getReferrerStatsPDA(program, referrer, stakingPool);
 
   try {
     const statsData = await program.account.referrerStats.fetch(referrerStats);
@@ -976,11 +1082,11 @@ export const fetchVaultBalances = async (
   try {
     // Fetch staking vault and reward vault in parallel
     const [stakingVaultAccount, rewardVaultAccount] = await Promise.all([
-      getAccount(connection, accounts.stakingVault).catch(e => {
+      getAccount(connection, accounts.stakingVault, undefined, accounts.stakingTokenProgram).catch(e => {
         if (!e.message?.includes('could not find account')) console.error('Error fetching staking vault:', e);
         return { amount: BigInt(0) }; // Return dummy object with amount 0
       }),
-      getAccount(connection, accounts.rewardVault).catch(e => {
+      getAccount(connection, accounts.rewardVault, undefined, accounts.rewardTokenProgram).catch(e => {
         if (!e.message?.includes('could not find account')) console.error('Error fetching reward vault:', e);
         return { amount: BigInt(0) };
       })